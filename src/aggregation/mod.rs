@@ -0,0 +1,387 @@
+//! Client-side aggregation of the counts, gauges and sets, and optionally of the histograms and
+//! distributions, see [AggregatingClient]
+
+mod sketch;
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, Weak},
+    thread,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    configuration::{AggregationOptions, SketchOptions},
+    error::Error,
+    value::{MetricValue, ValueBuffer},
+    ClientStats, DynDogstatsdClient, EventOptions, ServiceCheckOptions, ServiceStatus,
+};
+use sketch::Sketch;
+
+/// A [DynDogstatsdClient] aggregating the counts, gauges and sets of every context (metric name
+/// and tags) before sending them to the wrapped client, as the "extended aggregation" of the
+/// official Datadog clients. Every [AggregationOptions::flush_interval] a background thread sends
+/// the sum of the counts, the last value of the gauges and the unique members of the sets, so that
+/// a metric emitted thousands of times per second only results in a line per interval.
+///
+/// With [AggregationOptions::sketches], the values of the histograms and distributions are
+/// accumulated in a [DDSketch](https://arxiv.org/abs/1908.10693) per context as well, trading some
+/// accuracy for far fewer packets. They are sent as the `metric.count`, `metric.min`,
+/// `metric.max`, `metric.avg` gauges, and a gauge per percentile, e.g. `metric.median` and
+/// `metric.95percentile`, whose relative error is at most [SketchOptions::relative_accuracy].
+///
+/// The sample rate of the aggregated metrics is ignored, since every call is aggregated anyway.
+/// The other metrics, the counts and gauges with an explicit timestamp, the events and the service
+/// checks are sent to the wrapped client right away.
+///
+/// [DynDogstatsdClient::flush] and [DynDogstatsdClient::shutdown] send the aggregated values before
+/// flushing or shutting down the wrapped client, and so does dropping the client.
+///
+/// [Datadog::init](crate::Datadog::init) wraps its client when configured with
+/// [Configuration::with_aggregation](crate::configuration::Configuration::with_aggregation).
+///
+/// ```
+/// use prima_datadog::{
+///     aggregation::AggregatingClient,
+///     configuration::{AggregationOptions, Configuration},
+///     statsd::StatsdClient,
+///     Datadog, TagTrackerConfiguration,
+/// };
+///
+/// let client = StatsdClient::new(&Configuration::new("0.0.0.0:1234", "namespace")).unwrap();
+/// let client = AggregatingClient::new(client, AggregationOptions::default()).unwrap();
+/// let datadog = Datadog::new(client, TagTrackerConfiguration::new());
+/// ```
+pub struct AggregatingClient<C: DynDogstatsdClient + 'static> {
+    inner: Arc<Inner<C>>,
+}
+
+struct Inner<C: DynDogstatsdClient> {
+    client: C,
+    counts: Contexts<i64>,
+    gauges: Contexts<String>,
+    sets: Contexts<HashSet<String>>,
+    sketches: Option<SketchOptions>,
+    histograms: Contexts<Sketch>,
+    distributions: Contexts<Sketch>,
+}
+
+/// The aggregated values by context, keyed by `metric|#tags` as in the DogStatsD protocol
+type Contexts<T> = Mutex<HashMap<String, Aggregate<T>>>;
+
+struct Aggregate<T> {
+    metric: String,
+    tags: Vec<String>,
+    value: T,
+}
+
+impl<C: DynDogstatsdClient + 'static> AggregatingClient<C> {
+    /// Wraps the client, spawning the thread which sends the aggregated values every
+    /// [AggregationOptions::flush_interval]
+    ///
+    /// Fails if the [AggregationOptions::sketches] are not valid, see [SketchOptions].
+    pub fn new(client: C, options: AggregationOptions) -> Result<Self, Error> {
+        if let Some(sketches) = &options.sketches {
+            sketches.validate()?;
+        }
+        let inner = Arc::new(Inner {
+            client,
+            counts: Mutex::default(),
+            gauges: Mutex::default(),
+            sets: Mutex::default(),
+            sketches: options.sketches,
+            histograms: Mutex::default(),
+            distributions: Mutex::default(),
+        });
+        spawn_flusher(Arc::downgrade(&inner), options.flush_interval);
+        Ok(Self { inner })
+    }
+
+    fn add_count(&self, metric: &str, count: i64, tags: &[&str]) {
+        let aggregate = |total: &mut i64| *total = total.saturating_add(count);
+        aggregate_in(&self.inner.counts, metric, tags, 0, aggregate);
+    }
+
+    /// Adds the values to the sketch of the context, returning `false` if sketches are disabled or
+    /// a value is not a number, so that the values are sent as they are
+    fn add_to_sketch(&self, sketches: &Contexts<Sketch>, metric: &str, vals: &[&str], tags: &[&str]) -> bool {
+        let options = match &self.inner.sketches {
+            Some(options) => options,
+            None => return false,
+        };
+        let values: Option<Vec<f64>> = vals
+            .iter()
+            .map(|val| val.parse::<f64>().ok().filter(|value| value.is_finite()))
+            .collect();
+        let values = match values {
+            Some(values) => values,
+            None => return false,
+        };
+        let aggregate = |sketch: &mut Sketch| values.iter().for_each(|value| sketch.add(*value));
+        aggregate_in(
+            sketches,
+            metric,
+            tags,
+            Sketch::new(options.relative_accuracy),
+            aggregate,
+        );
+        true
+    }
+}
+
+impl<C: DynDogstatsdClient> Inner<C> {
+    /// Sends the aggregated values to the client, starting new aggregates
+    fn flush_aggregates(&self) {
+        for aggregate in take(&self.counts) {
+            // The configured sample rates must not be applied to the aggregated counts
+            let tags = tag_refs(&aggregate.tags);
+            self.client
+                .count_with_rate(&aggregate.metric, aggregate.value, 1.0, &tags);
+        }
+        for aggregate in take(&self.gauges) {
+            self.client
+                .gauge(&aggregate.metric, &aggregate.value, &tag_refs(&aggregate.tags));
+        }
+        for aggregate in take(&self.sets) {
+            let tags = tag_refs(&aggregate.tags);
+            for member in &aggregate.value {
+                self.client.set(&aggregate.metric, member, &tags);
+            }
+        }
+        if let Some(options) = &self.sketches {
+            for aggregate in take(&self.histograms).chain(take(&self.distributions)) {
+                self.send_summary(&aggregate, &options.percentiles);
+            }
+        }
+    }
+
+    /// Sends the count, min, max, avg and percentiles of the sketch as gauges
+    fn send_summary(&self, aggregate: &Aggregate<Sketch>, percentiles: &[f64]) {
+        let sketch = &aggregate.value;
+        let tags = tag_refs(&aggregate.tags);
+        let mut buffer = ValueBuffer::new();
+        let mut send = |suffix: &str, value: &dyn MetricValue| {
+            if let Some(value) = value.format_value(&mut buffer) {
+                let metric = format!("{}.{}", aggregate.metric, suffix);
+                self.client.gauge(&metric, value, &tags);
+            }
+        };
+        send("count", &sketch.count());
+        send("min", &sketch.min());
+        send("max", &sketch.max());
+        send("avg", &sketch.avg());
+        for percentile in percentiles {
+            if let Some(value) = sketch.quantile(*percentile) {
+                send(&percentile_suffix(*percentile), &value);
+            }
+        }
+    }
+}
+
+/// `median` for 0.5, otherwise the whole percent followed by `percentile`, e.g. `95percentile`
+fn percentile_suffix(percentile: f64) -> String {
+    if percentile == 0.5 {
+        "median".to_string()
+    } else {
+        format!("{}percentile", (percentile * 100.0).round() as u8)
+    }
+}
+
+impl<C: DynDogstatsdClient> Drop for Inner<C> {
+    fn drop(&mut self) {
+        self.flush_aggregates();
+    }
+}
+
+fn lock<T>(contexts: &Contexts<T>) -> MutexGuard<'_, HashMap<String, Aggregate<T>>> {
+    contexts.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Updates the value of the context, starting from `initial` the first time it is seen
+fn aggregate_in<T>(contexts: &Contexts<T>, metric: &str, tags: &[&str], initial: T, update: impl FnOnce(&mut T)) {
+    let key = format!("{}|#{}", metric, tags.join(","));
+    let mut contexts = lock(contexts);
+    let aggregate = contexts.entry(key).or_insert_with(|| Aggregate {
+        metric: metric.to_string(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        value: initial,
+    });
+    update(&mut aggregate.value);
+}
+
+fn take<T>(contexts: &Contexts<T>) -> impl Iterator<Item = Aggregate<T>> {
+    std::mem::take(&mut *lock(contexts)).into_values()
+}
+
+fn tag_refs(tags: &[String]) -> Vec<&str> {
+    tags.iter().map(String::as_str).collect()
+}
+
+/// Periodically sends the aggregated values, until the client is dropped
+fn spawn_flusher<C: DynDogstatsdClient + 'static>(inner: Weak<Inner<C>>, interval: Duration) {
+    let _ = thread::Builder::new()
+        .name("prima-datadog-aggregation".to_string())
+        .spawn(move || loop {
+            thread::sleep(interval);
+            match inner.upgrade() {
+                Some(inner) => inner.flush_aggregates(),
+                None => return,
+            }
+        });
+}
+
+#[async_trait]
+impl<C: DynDogstatsdClient + 'static> DynDogstatsdClient for AggregatingClient<C> {
+    fn incr(&self, metric: &str, tags: &[&str]) {
+        self.add_count(metric, 1, tags)
+    }
+
+    fn decr(&self, metric: &str, tags: &[&str]) {
+        self.add_count(metric, -1, tags)
+    }
+
+    fn count(&self, metric: &str, count: i64, tags: &[&str]) {
+        self.add_count(metric, count, tags)
+    }
+
+    fn time(&self, metric: &str, tags: &[&str], block: Box<dyn FnOnce() + '_>) {
+        self.inner.client.time(metric, tags, block)
+    }
+
+    async fn async_time<'a>(
+        &'a self,
+        metric: &'a str,
+        tags: &'a [&'a str],
+        block: Pin<Box<dyn Future<Output = ()> + Send + 'a>>,
+    ) {
+        self.inner.client.async_time(metric, tags, block).await
+    }
+
+    fn timing(&self, metric: &str, ms: i64, tags: &[&str]) {
+        self.inner.client.timing(metric, ms, tags)
+    }
+
+    fn timing_value(&self, metric: &str, val: &str, tags: &[&str]) {
+        self.inner.client.timing_value(metric, val, tags)
+    }
+
+    fn gauge(&self, metric: &str, val: &str, tags: &[&str]) {
+        let aggregate = |last: &mut String| {
+            last.clear();
+            last.push_str(val);
+        };
+        aggregate_in(&self.inner.gauges, metric, tags, String::new(), aggregate);
+    }
+
+    fn histogram(&self, metric: &str, val: &str, tags: &[&str]) {
+        if !self.add_to_sketch(&self.inner.histograms, metric, &[val], tags) {
+            self.inner.client.histogram(metric, val, tags)
+        }
+    }
+
+    fn distribution(&self, metric: &str, val: &str, tags: &[&str]) {
+        if !self.add_to_sketch(&self.inner.distributions, metric, &[val], tags) {
+            self.inner.client.distribution(metric, val, tags)
+        }
+    }
+
+    fn set(&self, metric: &str, val: &str, tags: &[&str]) {
+        let aggregate = |members: &mut HashSet<String>| {
+            if !members.contains(val) {
+                members.insert(val.to_string());
+            }
+        };
+        aggregate_in(&self.inner.sets, metric, tags, HashSet::new(), aggregate);
+    }
+
+    fn incr_with_rate(&self, metric: &str, _rate: f64, tags: &[&str]) {
+        self.add_count(metric, 1, tags)
+    }
+
+    fn decr_with_rate(&self, metric: &str, _rate: f64, tags: &[&str]) {
+        self.add_count(metric, -1, tags)
+    }
+
+    fn count_with_rate(&self, metric: &str, count: i64, _rate: f64, tags: &[&str]) {
+        self.add_count(metric, count, tags)
+    }
+
+    fn timing_with_rate(&self, metric: &str, ms: i64, rate: f64, tags: &[&str]) {
+        self.inner.client.timing_with_rate(metric, ms, rate, tags)
+    }
+
+    fn histogram_with_rate(&self, metric: &str, val: &str, rate: f64, tags: &[&str]) {
+        if !self.add_to_sketch(&self.inner.histograms, metric, &[val], tags) {
+            self.inner.client.histogram_with_rate(metric, val, rate, tags)
+        }
+    }
+
+    fn distribution_with_rate(&self, metric: &str, val: &str, rate: f64, tags: &[&str]) {
+        if !self.add_to_sketch(&self.inner.distributions, metric, &[val], tags) {
+            self.inner.client.distribution_with_rate(metric, val, rate, tags)
+        }
+    }
+
+    fn count_at(&self, metric: &str, count: i64, timestamp: u64, tags: &[&str]) {
+        self.inner.client.count_at(metric, count, timestamp, tags)
+    }
+
+    fn gauge_at(&self, metric: &str, val: &str, timestamp: u64, tags: &[&str]) {
+        self.inner.client.gauge_at(metric, val, timestamp, tags)
+    }
+
+    fn histogram_many(&self, metric: &str, vals: &[&str], tags: &[&str]) {
+        if !self.add_to_sketch(&self.inner.histograms, metric, vals, tags) {
+            self.inner.client.histogram_many(metric, vals, tags)
+        }
+    }
+
+    fn distribution_many(&self, metric: &str, vals: &[&str], tags: &[&str]) {
+        if !self.add_to_sketch(&self.inner.distributions, metric, vals, tags) {
+            self.inner.client.distribution_many(metric, vals, tags)
+        }
+    }
+
+    fn service_check(&self, metric: &str, val: ServiceStatus, tags: &[&str], options: Option<ServiceCheckOptions>) {
+        self.inner.client.service_check(metric, val, tags, options)
+    }
+
+    fn event(&self, title: &str, text: &str, tags: &[&str]) {
+        self.inner.client.event(title, text, tags)
+    }
+
+    fn event_with_options(&self, title: &str, text: &str, tags: &[&str], options: Option<EventOptions>) {
+        self.inner.client.event_with_options(title, text, tags, options)
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        self.inner.flush_aggregates();
+        self.inner.client.flush()
+    }
+
+    fn shutdown(&self, timeout: Duration) -> Result<(), Error> {
+        self.inner.flush_aggregates();
+        self.inner.client.shutdown(timeout)
+    }
+
+    fn stats(&self) -> ClientStats {
+        self.inner.client.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile_suffix() {
+        assert_eq!(percentile_suffix(0.5), "median");
+        assert_eq!(percentile_suffix(0.95), "95percentile");
+        assert_eq!(percentile_suffix(0.07), "7percentile");
+        assert_eq!(percentile_suffix(1.0), "100percentile");
+    }
+}
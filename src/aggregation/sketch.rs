@@ -0,0 +1,170 @@
+//! A DDSketch, whose quantiles are within a relative error of the actual ones.
+//!
+//! The values are counted in buckets whose bounds grow exponentially, so that a value `v` is
+//! stored in the bucket `ceil(log_gamma(|v|))` with `gamma = (1 + accuracy) / (1 - accuracy)`.
+//! The number of buckets only grows with the logarithm of the range of the values.
+//!
+//! See <https://arxiv.org/abs/1908.10693>
+
+use std::collections::BTreeMap;
+
+/// The values closer to 0 are counted as 0
+const MIN_INDEXABLE_VALUE: f64 = 1e-9;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Sketch {
+    gamma: f64,
+    gamma_ln: f64,
+    positive: BTreeMap<i32, u64>,
+    /// The buckets of the absolute values of the negative values
+    negative: BTreeMap<i32, u64>,
+    zeros: u64,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Sketch {
+    pub(crate) fn new(relative_accuracy: f64) -> Self {
+        let gamma = (1.0 + relative_accuracy) / (1.0 - relative_accuracy);
+        Self {
+            gamma,
+            gamma_ln: gamma.ln(),
+            positive: BTreeMap::new(),
+            negative: BTreeMap::new(),
+            zeros: 0,
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub(crate) fn add(&mut self, value: f64) {
+        if value > MIN_INDEXABLE_VALUE {
+            *self.positive.entry(self.index(value)).or_default() += 1;
+        } else if value < -MIN_INDEXABLE_VALUE {
+            *self.negative.entry(self.index(-value)).or_default() += 1;
+        } else {
+            self.zeros += 1;
+        }
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub(crate) fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub(crate) fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub(crate) fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub(crate) fn avg(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+
+    /// The value at the quantile `q` (between 0 and 1), or `None` if the sketch is empty
+    pub(crate) fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (q * (self.count - 1) as f64).round() as u64;
+        // The extremes are known exactly
+        if rank == 0 {
+            return Some(self.min);
+        }
+        if rank >= self.count - 1 {
+            return Some(self.max);
+        }
+        let mut seen = 0;
+        // From the lowest value: the negative values by decreasing absolute value, the zeros, then
+        // the positive values
+        for (&index, &count) in self.negative.iter().rev() {
+            seen += count;
+            if seen > rank {
+                return Some(-self.value(index).clamp(-self.max, -self.min));
+            }
+        }
+        seen += self.zeros;
+        if seen > rank {
+            return Some(0.0);
+        }
+        for (&index, &count) in &self.positive {
+            seen += count;
+            if seen > rank {
+                return Some(self.value(index).clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+
+    fn index(&self, value: f64) -> i32 {
+        (value.ln() / self.gamma_ln).ceil() as i32
+    }
+
+    /// The value within the relative accuracy of every value of the bucket
+    fn value(&self, index: i32) -> f64 {
+        2.0 * (self.gamma_ln * index as f64).exp() / (self.gamma + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_accurate(actual: f64, expected: f64, accuracy: f64) {
+        assert!(
+            (actual - expected).abs() <= expected.abs() * accuracy + 1e-12,
+            "{} is not within {} of {}",
+            actual,
+            accuracy,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_quantiles() {
+        let mut sketch = Sketch::new(0.01);
+        for value in 1..=1000 {
+            sketch.add(value as f64);
+        }
+
+        assert_eq!(sketch.count(), 1000);
+        assert_eq!(sketch.min(), 1.0);
+        assert_eq!(sketch.max(), 1000.0);
+        assert_eq!(sketch.avg(), 500.5);
+        assert_eq!(sketch.quantile(0.0), Some(1.0));
+        assert_eq!(sketch.quantile(1.0), Some(1000.0));
+        for q in [0.25, 0.5, 0.95, 0.99] {
+            let expected = (q * 999.0_f64).round() + 1.0;
+            assert_accurate(sketch.quantile(q).unwrap(), expected, 0.01);
+        }
+    }
+
+    #[test]
+    fn test_negative_values_and_zeros() {
+        let mut sketch = Sketch::new(0.02);
+        for value in [-100.0, -10.0, 0.0, 0.0, 10.0, 100.0] {
+            sketch.add(value);
+        }
+
+        assert_eq!(sketch.quantile(0.0), Some(-100.0));
+        assert_accurate(sketch.quantile(0.2).unwrap(), -10.0, 0.02);
+        assert_eq!(sketch.quantile(0.5), Some(0.0));
+        assert_accurate(sketch.quantile(0.8).unwrap(), 10.0, 0.02);
+        assert_eq!(sketch.quantile(1.0), Some(100.0));
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(Sketch::new(0.01).quantile(0.5), None);
+    }
+}
@@ -1,15 +1,267 @@
-use std::future::Future;
+use std::{
+    convert::TryInto,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
 use dogstatsd::EventOptions;
 
-use crate::{ServiceCheckOptions, ServiceStatus, TagsProvider};
+use crate::{error::Error, ServiceCheckOptions, ServiceStatus, TagsProvider};
 
 /// This trait represent a client that is able to interact with the datadog statsd collector.
+///
+/// This is the trait to implement for custom clients. It is object safe, so that the client can be
+/// chosen at runtime and stored as a `Box<dyn DynDogstatsdClient>`, which implements the trait as well.
+/// The generic [DogstatsdClient] API is automatically implemented on top of it.
+///
+/// ```
+/// use prima_datadog::{
+///     configuration::Configuration, Datadog, DynDogstatsdClient, EventOptions, ServiceCheckOptions, ServiceStatus,
+///     TagTrackerConfiguration,
+/// };
+///
+/// struct NoopClient;
+///
+/// impl DynDogstatsdClient for NoopClient {
+///     fn incr(&self, _: &str, _: &[&str]) {}
+///     fn decr(&self, _: &str, _: &[&str]) {}
+///     fn count(&self, _: &str, _: i64, _: &[&str]) {}
+///     fn timing(&self, _: &str, _: i64, _: &[&str]) {}
+///     fn gauge(&self, _: &str, _: &str, _: &[&str]) {}
+///     fn histogram(&self, _: &str, _: &str, _: &[&str]) {}
+///     fn distribution(&self, _: &str, _: &str, _: &[&str]) {}
+///     fn set(&self, _: &str, _: &str, _: &[&str]) {}
+///     fn service_check(&self, _: &str, _: ServiceStatus, _: &[&str], _: Option<ServiceCheckOptions>) {}
+///     fn event(&self, _: &str, _: &str, _: &[&str]) {}
+///     fn event_with_options(&self, _: &str, _: &str, _: &[&str], _: Option<EventOptions>) {}
+/// }
+///
+/// let client: Box<dyn DynDogstatsdClient> = if std::env::var("METRICS_ENABLED").is_ok() {
+///     Box::new(dogstatsd::Client::new(Configuration::new("0.0.0.0:1234", "namespace").into()).unwrap())
+/// } else {
+///     Box::new(NoopClient)
+/// };
+/// Datadog::init_with_client(client, TagTrackerConfiguration::new()).unwrap();
+/// ```
+#[async_trait]
+pub trait DynDogstatsdClient: Send + Sync {
+    /// Increment a StatsD counter
+    fn incr(&self, metric: &str, tags: &[&str]);
+
+    /// Decrement a StatsD counter
+    fn decr(&self, metric: &str, tags: &[&str]);
+
+    /// Make an arbitrary change to a StatsD counter
+    fn count(&self, metric: &str, count: i64, tags: &[&str]);
+
+    /// Time how long it takes for a block of code to execute.
+    ///
+    /// Implementations must run the block exactly once. By default the block is measured and
+    /// reported with [DynDogstatsdClient::timing].
+    fn time(&self, metric: &str, tags: &[&str], block: Box<dyn FnOnce() + '_>) {
+        let start = Instant::now();
+        block();
+        self.timing(metric, elapsed_millis(start), tags);
+    }
+
+    /// Time how long it takes for a future to complete.
+    ///
+    /// Implementations must await the future exactly once. By default the future is measured and
+    /// reported with [DynDogstatsdClient::timing].
+    async fn async_time<'a>(
+        &'a self,
+        metric: &'a str,
+        tags: &'a [&'a str],
+        block: Pin<Box<dyn Future<Output = ()> + Send + 'a>>,
+    ) {
+        let start = Instant::now();
+        block.await;
+        self.timing(metric, elapsed_millis(start), tags);
+    }
+
+    /// Send your own timing metric in milliseconds
+    fn timing(&self, metric: &str, ms: i64, tags: &[&str]);
+
+    /// Send a timing whose value is already formatted, e.g. with a decimal part or in a unit other
+    /// than milliseconds, see [TimingOptions](crate::configuration::TimingOptions).
+    ///
+    /// By default the value is rounded and sent with [DynDogstatsdClient::timing].
+    fn timing_value(&self, metric: &str, val: &str, tags: &[&str]) {
+        if let Ok(value) = val.parse::<f64>() {
+            self.timing(metric, value.round() as i64, tags);
+        }
+    }
+
+    /// Report an arbitrary value as a gauge
+    fn gauge(&self, metric: &str, val: &str, tags: &[&str]);
+
+    /// Report a value in a histogram
+    fn histogram(&self, metric: &str, val: &str, tags: &[&str]);
+
+    /// Report a value in a distribution
+    fn distribution(&self, metric: &str, val: &str, tags: &[&str]);
+
+    /// Report a value in a set
+    fn set(&self, metric: &str, val: &str, tags: &[&str]);
+
+    /// Increment a StatsD counter, sending only a `rate` fraction (between 0 and 1) of the calls
+    /// with the rate, so that the agent scales the value back up.
+    ///
+    /// Clients which can't send the rate don't need to implement the `*_with_rate` methods: by
+    /// default every call is sent without sampling, so that the values stay correct.
+    fn incr_with_rate(&self, metric: &str, rate: f64, tags: &[&str]) {
+        let _ = rate;
+        self.incr(metric, tags)
+    }
+
+    /// Decrement a StatsD counter, sampled at `rate`, see [DynDogstatsdClient::incr_with_rate]
+    fn decr_with_rate(&self, metric: &str, rate: f64, tags: &[&str]) {
+        let _ = rate;
+        self.decr(metric, tags)
+    }
+
+    /// Make an arbitrary change to a StatsD counter, sampled at `rate`, see
+    /// [DynDogstatsdClient::incr_with_rate]
+    fn count_with_rate(&self, metric: &str, count: i64, rate: f64, tags: &[&str]) {
+        let _ = rate;
+        self.count(metric, count, tags)
+    }
+
+    /// Send your own timing metric in milliseconds, sampled at `rate`, see
+    /// [DynDogstatsdClient::incr_with_rate]
+    fn timing_with_rate(&self, metric: &str, ms: i64, rate: f64, tags: &[&str]) {
+        let _ = rate;
+        self.timing(metric, ms, tags)
+    }
+
+    /// Report a value in a histogram, sampled at `rate`, see [DynDogstatsdClient::incr_with_rate]
+    fn histogram_with_rate(&self, metric: &str, val: &str, rate: f64, tags: &[&str]) {
+        let _ = rate;
+        self.histogram(metric, val, tags)
+    }
+
+    /// Report a value in a distribution, sampled at `rate`, see
+    /// [DynDogstatsdClient::incr_with_rate]
+    fn distribution_with_rate(&self, metric: &str, val: &str, rate: f64, tags: &[&str]) {
+        let _ = rate;
+        self.distribution(metric, val, tags)
+    }
+
+    /// Make an arbitrary change to a StatsD counter, as of `timestamp` in seconds since the unix
+    /// epoch, e.g. to submit historical data.
+    ///
+    /// Explicit timestamps are only accepted for counts and gauges, by the DogStatsD protocol v1.3.
+    /// By default the timestamp is ignored and the value is sent with [DynDogstatsdClient::count].
+    fn count_at(&self, metric: &str, count: i64, timestamp: u64, tags: &[&str]) {
+        let _ = timestamp;
+        self.count(metric, count, tags)
+    }
+
+    /// Report an arbitrary value as a gauge, as of `timestamp` in seconds since the unix epoch, see
+    /// [DynDogstatsdClient::count_at]
+    fn gauge_at(&self, metric: &str, val: &str, timestamp: u64, tags: &[&str]) {
+        let _ = timestamp;
+        self.gauge(metric, val, tags)
+    }
+
+    /// Report several values in a histogram at once.
+    ///
+    /// By default every value is reported with [DynDogstatsdClient::histogram], clients supporting
+    /// the DogStatsD protocol v1.1 can pack them in a single line, e.g. `metric:1:2:3|h`.
+    fn histogram_many(&self, metric: &str, vals: &[&str], tags: &[&str]) {
+        for val in vals {
+            self.histogram(metric, val, tags);
+        }
+    }
+
+    /// Report several values in a distribution at once, see [DynDogstatsdClient::histogram_many]
+    fn distribution_many(&self, metric: &str, vals: &[&str], tags: &[&str]) {
+        for val in vals {
+            self.distribution(metric, val, tags);
+        }
+    }
+
+    /// Report the status of a service
+    fn service_check(&self, metric: &str, val: ServiceStatus, tags: &[&str], options: Option<ServiceCheckOptions>);
+
+    /// Send a custom event as a title and a body
+    fn event(&self, title: &str, text: &str, tags: &[&str]);
+
+    /// Send a custom event as a title, a body and some options
+    fn event_with_options(&self, title: &str, text: &str, tags: &[&str], options: Option<EventOptions>);
+
+    /// Sends every metric buffered by the client, blocking until they have been handed to the
+    /// transport.
+    ///
+    /// Clients which don't buffer metrics don't need to implement it.
+    fn flush(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Flushes the client and stops it, waiting at most `timeout`. Metrics emitted after the
+    /// shutdown are dropped.
+    ///
+    /// By default it is the same as [DynDogstatsdClient::flush].
+    fn shutdown(&self, timeout: Duration) -> Result<(), Error> {
+        let _ = timeout;
+        self.flush()
+    }
+
+    /// A snapshot of the internal counters of the client.
+    ///
+    /// Clients which don't keep counters don't need to implement it.
+    fn stats(&self) -> ClientStats {
+        ClientStats::default()
+    }
+}
+
+/// The internal counters of a client, see [DynDogstatsdClient::stats]
+///
+/// The counters are cumulative since the creation of the client.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ClientStats {
+    /// Metrics emitted through the client
+    pub metrics: u64,
+    /// Events emitted through the client
+    pub events: u64,
+    /// Service checks emitted through the client
+    pub service_checks: u64,
+    /// Packets successfully sent to the agent
+    pub packets_sent: u64,
+    /// Bytes successfully sent to the agent
+    pub bytes_sent: u64,
+    /// Packets which could not be sent to the agent
+    pub packets_dropped: u64,
+    /// Bytes which could not be sent to the agent
+    pub bytes_dropped: u64,
+    /// Metrics dropped because the queue of the background worker was full, or because they were
+    /// emitted after the shutdown
+    pub dropped_items: u64,
+    /// Metrics, events and service checks dropped because they can't fit in a single packet, see
+    /// [Configuration::with_max_payload_size](crate::configuration::Configuration::with_max_payload_size)
+    pub oversized_payloads: u64,
+    /// Actions taken by the tag tracker when the cardinality threshold was exceeded. Filled by
+    /// [Datadog::stats](crate::Datadog::stats), and by the [StatsdClient](crate::statsd::StatsdClient)
+    /// created from the configuration holding the tracker configuration.
+    pub tracker_events: u64,
+    /// Values which were not sent because they can't be represented, e.g. NaN or infinite floats,
+    /// or because their timestamp is in the future.
+    /// Only filled by [Datadog::stats](crate::Datadog::stats).
+    pub invalid_values: u64,
+    /// Polls of the gauges registered with [Datadog::register_gauge](crate::Datadog::register_gauge)
+    /// whose callback panicked. Only filled by [Datadog::stats](crate::Datadog::stats).
+    pub gauge_callback_panics: u64,
+}
+
+/// The generic API of a client, automatically implemented for every [DynDogstatsdClient].
+///
 /// Its main use in this library is having a common interface for the underlying implementation,
-/// and being able to mock it for testing purposes
+/// accepting any kind of tags, and being able to mock it for testing purposes
 #[async_trait]
-pub trait DogstatsdClient {
+pub trait DogstatsdClient: DynDogstatsdClient {
     /// Increment a StatsD counter
     fn incr<S>(&self, metric: &str, tags: impl TagsProvider<S>)
     where
@@ -35,13 +287,19 @@ pub trait DogstatsdClient {
     where
         S: AsRef<str> + Sync,
         F: FnOnce() -> T + Send,
-        T: Future<Output = O> + Send;
+        T: Future<Output = O> + Send,
+        O: Send;
 
     /// Send your own timing metric in milliseconds
     fn timing<S>(&self, metric: &str, ms: i64, tags: impl TagsProvider<S>)
     where
         S: AsRef<str>;
 
+    /// Send a timing whose value is already formatted
+    fn timing_value<S>(&self, metric: &str, val: &str, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>;
+
     /// Report an arbitrary value as a gauge
     fn gauge<S>(&self, metric: &str, val: &str, tags: impl TagsProvider<S>)
     where
@@ -62,6 +320,57 @@ pub trait DogstatsdClient {
     where
         S: AsRef<str>;
 
+    /// Increment a StatsD counter, sampled at `rate`
+    fn incr_with_rate<S>(&self, metric: &str, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>;
+
+    /// Decrement a StatsD counter, sampled at `rate`
+    fn decr_with_rate<S>(&self, metric: &str, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>;
+
+    /// Make an arbitrary change to a StatsD counter, sampled at `rate`
+    fn count_with_rate<S>(&self, metric: &str, count: i64, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>;
+
+    /// Send your own timing metric in milliseconds, sampled at `rate`
+    fn timing_with_rate<S>(&self, metric: &str, ms: i64, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>;
+
+    /// Report a value in a histogram, sampled at `rate`
+    fn histogram_with_rate<S>(&self, metric: &str, val: &str, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>;
+
+    /// Report a value in a distribution, sampled at `rate`
+    fn distribution_with_rate<S>(&self, metric: &str, val: &str, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>;
+
+    /// Make an arbitrary change to a StatsD counter, as of `timestamp` in seconds since the unix
+    /// epoch
+    fn count_at<S>(&self, metric: &str, count: i64, timestamp: u64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>;
+
+    /// Report an arbitrary value as a gauge, as of `timestamp` in seconds since the unix epoch
+    fn gauge_at<S>(&self, metric: &str, val: &str, timestamp: u64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>;
+
+    /// Report several values in a histogram at once
+    fn histogram_many<S>(&self, metric: &str, vals: &[&str], tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>;
+
+    /// Report several values in a distribution at once
+    fn distribution_many<S>(&self, metric: &str, vals: &[&str], tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>;
+
     /// Report the status of a service
     fn service_check<S>(
         &self,
@@ -84,26 +393,26 @@ pub trait DogstatsdClient {
 }
 
 #[async_trait]
-impl DogstatsdClient for dogstatsd::Client {
+impl<C: DynDogstatsdClient + ?Sized> DogstatsdClient for C {
     fn incr<S>(&self, metric: &str, tags: impl TagsProvider<S>)
     where
         S: AsRef<str>,
     {
-        let _ = self.incr(metric, tags.as_ref());
+        DynDogstatsdClient::incr(self, metric, &tag_refs(&tags));
     }
 
     fn decr<S>(&self, metric: &str, tags: impl TagsProvider<S>)
     where
         S: AsRef<str>,
     {
-        let _ = self.decr(metric, tags.as_ref());
+        DynDogstatsdClient::decr(self, metric, &tag_refs(&tags));
     }
 
     fn count<S>(&self, metric: &str, count: i64, tags: impl TagsProvider<S>)
     where
         S: AsRef<str>,
     {
-        let _ = self.count(metric, count, tags.as_ref());
+        DynDogstatsdClient::count(self, metric, count, &tag_refs(&tags));
     }
 
     fn time<S, F, O>(&self, metric: &str, tags: impl TagsProvider<S>, block: F) -> O
@@ -111,7 +420,9 @@ impl DogstatsdClient for dogstatsd::Client {
         S: AsRef<str>,
         F: FnOnce() -> O,
     {
-        self.time(metric, tags.as_ref(), block).unwrap_or_else(|(o, _)| o)
+        let mut output = None;
+        DynDogstatsdClient::time(self, metric, &tag_refs(&tags), Box::new(|| output = Some(block())));
+        output.expect("DynDogstatsdClient::time must run the timed block")
     }
 
     async fn async_time<S, F, T, O>(&self, metric: &str, tags: impl TagsProvider<S> + Send + Sync, block: F) -> O
@@ -119,45 +430,124 @@ impl DogstatsdClient for dogstatsd::Client {
         S: AsRef<str> + Sync,
         F: FnOnce() -> T + Send,
         T: Future<Output = O> + Send,
+        O: Send,
     {
-        self.async_time(metric, tags.as_ref(), block)
-            .await
-            .unwrap_or_else(|(o, _)| o)
+        let mut output = None;
+        let tags = tag_refs(&tags);
+        DynDogstatsdClient::async_time(self, metric, &tags, Box::pin(async { output = Some(block().await) })).await;
+        output.expect("DynDogstatsdClient::async_time must await the timed future")
     }
 
     fn timing<S>(&self, metric: &str, ms: i64, tags: impl TagsProvider<S>)
     where
         S: AsRef<str>,
     {
-        let _ = self.timing(metric, ms, tags.as_ref());
+        DynDogstatsdClient::timing(self, metric, ms, &tag_refs(&tags));
+    }
+
+    fn timing_value<S>(&self, metric: &str, val: &str, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>,
+    {
+        DynDogstatsdClient::timing_value(self, metric, val, &tag_refs(&tags));
     }
 
     fn gauge<S>(&self, metric: &str, val: &str, tags: impl TagsProvider<S>)
     where
         S: AsRef<str>,
     {
-        let _ = self.gauge(metric, val, tags.as_ref());
+        DynDogstatsdClient::gauge(self, metric, val, &tag_refs(&tags));
     }
 
     fn histogram<S>(&self, metric: &str, val: &str, tags: impl TagsProvider<S>)
     where
         S: AsRef<str>,
     {
-        let _ = self.histogram(metric, val, tags.as_ref());
+        DynDogstatsdClient::histogram(self, metric, val, &tag_refs(&tags));
     }
 
     fn distribution<S>(&self, metric: &str, val: &str, tags: impl TagsProvider<S>)
     where
         S: AsRef<str>,
     {
-        let _ = self.distribution(metric, val, tags.as_ref());
+        DynDogstatsdClient::distribution(self, metric, val, &tag_refs(&tags));
     }
 
     fn set<S>(&self, metric: &str, val: &str, tags: impl TagsProvider<S>)
     where
         S: AsRef<str>,
     {
-        let _ = self.set(metric, val, tags.as_ref());
+        DynDogstatsdClient::set(self, metric, val, &tag_refs(&tags));
+    }
+
+    fn incr_with_rate<S>(&self, metric: &str, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>,
+    {
+        DynDogstatsdClient::incr_with_rate(self, metric, rate, &tag_refs(&tags));
+    }
+
+    fn decr_with_rate<S>(&self, metric: &str, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>,
+    {
+        DynDogstatsdClient::decr_with_rate(self, metric, rate, &tag_refs(&tags));
+    }
+
+    fn count_with_rate<S>(&self, metric: &str, count: i64, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>,
+    {
+        DynDogstatsdClient::count_with_rate(self, metric, count, rate, &tag_refs(&tags));
+    }
+
+    fn timing_with_rate<S>(&self, metric: &str, ms: i64, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>,
+    {
+        DynDogstatsdClient::timing_with_rate(self, metric, ms, rate, &tag_refs(&tags));
+    }
+
+    fn histogram_with_rate<S>(&self, metric: &str, val: &str, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>,
+    {
+        DynDogstatsdClient::histogram_with_rate(self, metric, val, rate, &tag_refs(&tags));
+    }
+
+    fn distribution_with_rate<S>(&self, metric: &str, val: &str, rate: f64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>,
+    {
+        DynDogstatsdClient::distribution_with_rate(self, metric, val, rate, &tag_refs(&tags));
+    }
+
+    fn count_at<S>(&self, metric: &str, count: i64, timestamp: u64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>,
+    {
+        DynDogstatsdClient::count_at(self, metric, count, timestamp, &tag_refs(&tags));
+    }
+
+    fn gauge_at<S>(&self, metric: &str, val: &str, timestamp: u64, tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>,
+    {
+        DynDogstatsdClient::gauge_at(self, metric, val, timestamp, &tag_refs(&tags));
+    }
+
+    fn histogram_many<S>(&self, metric: &str, vals: &[&str], tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>,
+    {
+        DynDogstatsdClient::histogram_many(self, metric, vals, &tag_refs(&tags));
+    }
+
+    fn distribution_many<S>(&self, metric: &str, vals: &[&str], tags: impl TagsProvider<S>)
+    where
+        S: AsRef<str>,
+    {
+        DynDogstatsdClient::distribution_many(self, metric, vals, &tag_refs(&tags));
     }
 
     fn service_check<S>(
@@ -169,20 +559,217 @@ impl DogstatsdClient for dogstatsd::Client {
     ) where
         S: AsRef<str>,
     {
-        let _ = self.service_check(metric, val, tags.as_ref(), options);
+        DynDogstatsdClient::service_check(self, metric, val, &tag_refs(&tags), options);
     }
 
     fn event<S>(&self, title: &str, text: &str, tags: impl TagsProvider<S>)
     where
         S: AsRef<str>,
     {
-        let _ = self.event(title, text, tags.as_ref());
+        DynDogstatsdClient::event(self, title, text, &tag_refs(&tags));
     }
 
     fn event_with_options<S>(&self, title: &str, text: &str, tags: impl TagsProvider<S>, options: Option<EventOptions>)
     where
         S: AsRef<str>,
     {
-        let _ = self.event_with_options(title, text, tags.as_ref(), options);
+        DynDogstatsdClient::event_with_options(self, title, text, &tag_refs(&tags), options);
+    }
+}
+
+fn tag_refs<'a, S: AsRef<str> + 'a>(tags: &'a impl TagsProvider<S>) -> Vec<&'a str> {
+    tags.as_ref().iter().map(|tag| tag.as_ref()).collect()
+}
+
+pub(crate) fn elapsed_millis(start: Instant) -> i64 {
+    duration_millis(start.elapsed())
+}
+
+pub(crate) fn duration_millis(duration: Duration) -> i64 {
+    duration.as_millis().try_into().unwrap_or(i64::MAX)
+}
+
+/// The client type stored in the global instance
+pub(crate) type GlobalClient = Box<dyn DynDogstatsdClient>;
+
+macro_rules! forward_dyn_dogstatsd_client {
+    ($ty:ty) => {
+        #[async_trait]
+        impl<C: DynDogstatsdClient + ?Sized> DynDogstatsdClient for $ty {
+            fn incr(&self, metric: &str, tags: &[&str]) {
+                (**self).incr(metric, tags)
+            }
+
+            fn decr(&self, metric: &str, tags: &[&str]) {
+                (**self).decr(metric, tags)
+            }
+
+            fn count(&self, metric: &str, count: i64, tags: &[&str]) {
+                (**self).count(metric, count, tags)
+            }
+
+            fn time(&self, metric: &str, tags: &[&str], block: Box<dyn FnOnce() + '_>) {
+                (**self).time(metric, tags, block)
+            }
+
+            async fn async_time<'a>(
+                &'a self,
+                metric: &'a str,
+                tags: &'a [&'a str],
+                block: Pin<Box<dyn Future<Output = ()> + Send + 'a>>,
+            ) {
+                (**self).async_time(metric, tags, block).await
+            }
+
+            fn timing(&self, metric: &str, ms: i64, tags: &[&str]) {
+                (**self).timing(metric, ms, tags)
+            }
+
+            fn timing_value(&self, metric: &str, val: &str, tags: &[&str]) {
+                (**self).timing_value(metric, val, tags)
+            }
+
+            fn gauge(&self, metric: &str, val: &str, tags: &[&str]) {
+                (**self).gauge(metric, val, tags)
+            }
+
+            fn histogram(&self, metric: &str, val: &str, tags: &[&str]) {
+                (**self).histogram(metric, val, tags)
+            }
+
+            fn distribution(&self, metric: &str, val: &str, tags: &[&str]) {
+                (**self).distribution(metric, val, tags)
+            }
+
+            fn set(&self, metric: &str, val: &str, tags: &[&str]) {
+                (**self).set(metric, val, tags)
+            }
+
+            fn incr_with_rate(&self, metric: &str, rate: f64, tags: &[&str]) {
+                (**self).incr_with_rate(metric, rate, tags)
+            }
+
+            fn decr_with_rate(&self, metric: &str, rate: f64, tags: &[&str]) {
+                (**self).decr_with_rate(metric, rate, tags)
+            }
+
+            fn count_with_rate(&self, metric: &str, count: i64, rate: f64, tags: &[&str]) {
+                (**self).count_with_rate(metric, count, rate, tags)
+            }
+
+            fn timing_with_rate(&self, metric: &str, ms: i64, rate: f64, tags: &[&str]) {
+                (**self).timing_with_rate(metric, ms, rate, tags)
+            }
+
+            fn histogram_with_rate(&self, metric: &str, val: &str, rate: f64, tags: &[&str]) {
+                (**self).histogram_with_rate(metric, val, rate, tags)
+            }
+
+            fn distribution_with_rate(&self, metric: &str, val: &str, rate: f64, tags: &[&str]) {
+                (**self).distribution_with_rate(metric, val, rate, tags)
+            }
+
+            fn count_at(&self, metric: &str, count: i64, timestamp: u64, tags: &[&str]) {
+                (**self).count_at(metric, count, timestamp, tags)
+            }
+
+            fn gauge_at(&self, metric: &str, val: &str, timestamp: u64, tags: &[&str]) {
+                (**self).gauge_at(metric, val, timestamp, tags)
+            }
+
+            fn histogram_many(&self, metric: &str, vals: &[&str], tags: &[&str]) {
+                (**self).histogram_many(metric, vals, tags)
+            }
+
+            fn distribution_many(&self, metric: &str, vals: &[&str], tags: &[&str]) {
+                (**self).distribution_many(metric, vals, tags)
+            }
+
+            fn service_check(
+                &self,
+                metric: &str,
+                val: ServiceStatus,
+                tags: &[&str],
+                options: Option<ServiceCheckOptions>,
+            ) {
+                (**self).service_check(metric, val, tags, options)
+            }
+
+            fn event(&self, title: &str, text: &str, tags: &[&str]) {
+                (**self).event(title, text, tags)
+            }
+
+            fn event_with_options(&self, title: &str, text: &str, tags: &[&str], options: Option<EventOptions>) {
+                (**self).event_with_options(title, text, tags, options)
+            }
+
+            fn flush(&self) -> Result<(), Error> {
+                (**self).flush()
+            }
+
+            fn shutdown(&self, timeout: Duration) -> Result<(), Error> {
+                (**self).shutdown(timeout)
+            }
+
+            fn stats(&self) -> ClientStats {
+                (**self).stats()
+            }
+        }
+    };
+}
+
+forward_dyn_dogstatsd_client!(Box<C>);
+forward_dyn_dogstatsd_client!(Arc<C>);
+
+/// The batching thread of [dogstatsd::Client] can't be flushed, so `flush` and `shutdown` have no
+/// effect on it: use [StatsdClient](crate::statsd::StatsdClient) to flush batched metrics. It can't
+/// send sample rates either, so the `*_with_rate` methods send every call.
+impl DynDogstatsdClient for dogstatsd::Client {
+    fn incr(&self, metric: &str, tags: &[&str]) {
+        let _ = self.incr(metric, tags);
+    }
+
+    fn decr(&self, metric: &str, tags: &[&str]) {
+        let _ = self.decr(metric, tags);
+    }
+
+    fn count(&self, metric: &str, count: i64, tags: &[&str]) {
+        let _ = self.count(metric, count, tags);
+    }
+
+    fn time(&self, metric: &str, tags: &[&str], block: Box<dyn FnOnce() + '_>) {
+        let _ = self.time(metric, tags, block);
+    }
+
+    fn timing(&self, metric: &str, ms: i64, tags: &[&str]) {
+        let _ = self.timing(metric, ms, tags);
+    }
+
+    fn gauge(&self, metric: &str, val: &str, tags: &[&str]) {
+        let _ = self.gauge(metric, val, tags);
+    }
+
+    fn histogram(&self, metric: &str, val: &str, tags: &[&str]) {
+        let _ = self.histogram(metric, val, tags);
+    }
+
+    fn distribution(&self, metric: &str, val: &str, tags: &[&str]) {
+        let _ = self.distribution(metric, val, tags);
+    }
+
+    fn set(&self, metric: &str, val: &str, tags: &[&str]) {
+        let _ = self.set(metric, val, tags);
+    }
+
+    fn service_check(&self, metric: &str, val: ServiceStatus, tags: &[&str], options: Option<ServiceCheckOptions>) {
+        let _ = self.service_check(metric, val, tags, options);
+    }
+
+    fn event(&self, title: &str, text: &str, tags: &[&str]) {
+        let _ = self.event(title, text, tags);
+    }
+
+    fn event_with_options(&self, title: &str, text: &str, tags: &[&str], options: Option<EventOptions>) {
+        let _ = self.event_with_options(title, text, tags, options);
     }
 }
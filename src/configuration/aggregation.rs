@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use crate::error::Error;
+
+/// Options of the client-side aggregation, see
+/// [AggregatingClient](crate::aggregation::AggregatingClient).
+#[derive(PartialEq, Debug, Clone)]
+pub struct AggregationOptions {
+    /// How often the aggregated values are sent
+    pub flush_interval: Duration,
+    /// Aggregates the histograms and distributions in sketches as well, sending their summary as
+    /// gauges instead of every value. Disabled by default.
+    pub sketches: Option<SketchOptions>,
+}
+
+impl Default for AggregationOptions {
+    /// Flushes every 2 seconds, as the official Datadog clients do
+    fn default() -> Self {
+        Self {
+            flush_interval: Duration::from_secs(2),
+            sketches: None,
+        }
+    }
+}
+
+/// Options of the sketches aggregating the histograms and distributions, see
+/// [AggregatingClient](crate::aggregation::AggregatingClient).
+#[derive(PartialEq, Debug, Clone)]
+pub struct SketchOptions {
+    /// The maximum relative error of the percentiles, e.g. `0.01` for 1%. It must be between 0
+    /// and 1 (excluded): the lower, the more memory a sketch takes.
+    pub relative_accuracy: f64,
+    /// The percentiles sent, whole percents between 0 and 1, e.g. `0.95` is sent as
+    /// `metric.95percentile` and `0.5` as `metric.median`. As for the agent, fractional percents
+    /// such as `0.999` are not supported.
+    pub percentiles: Vec<f64>,
+}
+
+impl SketchOptions {
+    /// Fails if the relative accuracy or a percentile is out of its bounds, or if a percentile is
+    /// not a whole percent
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if !(self.relative_accuracy > 0.0 && self.relative_accuracy < 1.0) {
+            return Err(Error::InvalidSketchOptions(
+                "the relative accuracy must be between 0 and 1 (excluded)",
+            ));
+        }
+        if !self
+            .percentiles
+            .iter()
+            .all(|percentile| (0.0..=1.0).contains(percentile) && is_whole_percent(*percentile))
+        {
+            return Err(Error::InvalidSketchOptions(
+                "the percentiles must be whole percents between 0 and 1",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for SketchOptions {
+    /// A 1% relative accuracy, sending the median and the 95th and 99th percentiles
+    fn default() -> Self {
+        Self {
+            relative_accuracy: 0.01,
+            percentiles: vec![0.5, 0.95, 0.99],
+        }
+    }
+}
+
+/// Whether the percentile is a whole percent, e.g. `0.95` but not `0.999`, so that its name has
+/// no `.` in it
+fn is_whole_percent(percentile: f64) -> bool {
+    let percent = percentile * 100.0;
+    (percent - percent.round()).abs() < 1e-9
+}
@@ -2,24 +2,49 @@ use std::{fmt::Display, str::FromStr};
 
 use crate::error::Error;
 
+use super::is_valid_tag_value;
+
 /// Represents the country in which the datadog client runs.
 /// This is useful for enforcing rules based on country for every application that uses the library.
+///
+/// Parsing is case insensitive and accepts `gb` as an alias of `uk`. Any other valid tag value is
+/// parsed as [Country::Custom], so that new markets don't need a new release of this crate.
 #[derive(PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
 pub enum Country {
     Common,
     It,
     Es,
     Uk,
+    Fr,
+    De,
+    Pt,
+    /// Any other country. Use [Country::custom] to check that it is a valid tag value.
+    Custom(String),
 }
 
 impl Country {
+    /// Creates a [Country::Custom] country, failing if it is not a valid tag value
+    pub fn custom(country: &str) -> Result<Self, Error> {
+        let country = country.to_lowercase();
+        if is_valid_tag_value(&country) {
+            Ok(Self::Custom(country))
+        } else {
+            Err(Error::WrongCountryDefinition)
+        }
+    }
+
     /// Returns the string representation of the country.
-    pub fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &str {
         match self {
             Country::Common => "common",
             Country::It => "it",
             Country::Es => "es",
             Country::Uk => "uk",
+            Country::Fr => "fr",
+            Country::De => "de",
+            Country::Pt => "pt",
+            Country::Custom(country) => country,
         }
     }
 }
@@ -28,12 +53,15 @@ impl FromStr for Country {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
+        match s.to_lowercase().as_str() {
             "common" => Ok(Self::Common),
             "it" => Ok(Self::It),
             "es" => Ok(Self::Es),
-            "uk" => Ok(Self::Uk),
-            _ => Err(Error::WrongCountryDefinition),
+            "uk" | "gb" => Ok(Self::Uk),
+            "fr" => Ok(Self::Fr),
+            "de" => Ok(Self::De),
+            "pt" => Ok(Self::Pt),
+            _ => Self::custom(s),
         }
     }
 }
@@ -63,13 +91,24 @@ impl<'de> serde::Deserialize<'de> for Country {
             where
                 E: serde::de::Error,
             {
-                Country::from_str(v).map_err(|_| E::custom("unknown country tag"))
+                Country::from_str(v).map_err(|_| E::custom(format!("invalid country tag `{v}`")))
             }
         }
         deserializer.deserialize_str(CountryVisitor)
     }
 }
 
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+#[cfg(feature = "serde")]
+impl serde::Serialize for Country {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::configuration::Configuration;
@@ -92,4 +131,14 @@ mod tests {
         // Datadog tag keys are allowed to map to multiple values, and I suppose we're ok with that too (e.g. cross-country infra down the line?)
         assert_eq!(config.default_tags(), vec!["prima_country:it", "prima_country:es"]);
     }
+
+    #[test]
+    pub fn test_from_str() {
+        assert_eq!(Some(Country::It), "IT".parse().ok());
+        assert_eq!(Some(Country::Uk), "gb".parse().ok());
+        assert_eq!(Some(Country::Fr), "fr".parse().ok());
+        assert_eq!(Some(Country::Custom("nl".to_string())), "NL".parse().ok());
+        assert_eq!(None, "n l".parse::<Country>().ok());
+        assert!(Country::custom("").is_err());
+    }
 }
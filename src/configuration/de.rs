@@ -0,0 +1,346 @@
+//! Deserialization of a [Configuration], e.g. from a configuration file
+
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+use super::{
+    AggregationOptions, BatchingOptions, Configuration, Country, Environment, OverflowPolicy, SketchOptions,
+    TimingOptions, TimingUnit, WorkerOptions,
+};
+use crate::TagTrackerConfiguration;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigurationDef {
+    to_addr: String,
+    from_addr: Option<String>,
+    #[serde(default)]
+    namespace: String,
+    environment: Option<Environment>,
+    country: Option<Country>,
+    service: Option<String>,
+    version: Option<String>,
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+    socket_path: Option<String>,
+    #[serde(default)]
+    udp_fallback: bool,
+    max_payload_size: Option<usize>,
+    batching: Option<BatchingDef>,
+    worker: Option<WorkerDef>,
+    aggregation: Option<AggregationDef>,
+    /// `0` disables the telemetry
+    telemetry_interval_ms: Option<u64>,
+    gauge_interval_ms: Option<u64>,
+    error_rate_limit_ms: Option<u64>,
+    #[serde(default)]
+    sample_rates: BTreeMap<String, f64>,
+    timing: Option<TimingDef>,
+    #[serde(default)]
+    origin_detection: bool,
+    #[serde(default)]
+    validation: bool,
+    container_id: Option<String>,
+    tracker: Option<TrackerDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchingDef {
+    max_buffer_size: usize,
+    max_time_ms: u64,
+    max_retry_attempts: usize,
+    initial_retry_delay_ms: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkerDef {
+    queue_capacity: Option<usize>,
+    overflow_policy: Option<OverflowPolicy>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AggregationDef {
+    flush_interval_ms: Option<u64>,
+    sketches: Option<SketchDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SketchDef {
+    relative_accuracy: Option<f64>,
+    percentiles: Option<Vec<f64>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TimingDef {
+    #[serde(default)]
+    unit: TimingUnit,
+    #[serde(default)]
+    as_distribution: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TrackerDef {
+    threshold: Option<usize>,
+    #[serde(default)]
+    actions: Vec<ActionDef>,
+}
+
+/// The tracker actions which can be configured by name, custom actions have to be added in code
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+enum ActionDef {
+    Event { title: String, text: String },
+}
+
+impl ConfigurationDef {
+    fn into_configuration(self) -> Result<Configuration, String> {
+        if self.to_addr.is_empty() && self.socket_path.is_none() {
+            return Err("to_addr must not be empty unless socket_path is set".to_string());
+        }
+        let mut configuration = Configuration::new(&self.to_addr, &self.namespace);
+        if let Some(from_addr) = self.from_addr {
+            configuration = configuration.with_from_addr(&from_addr);
+        }
+        if let Some(environment) = self.environment {
+            configuration = configuration.with_environment(environment);
+        }
+        if let Some(country) = self.country {
+            configuration = configuration.with_country(country);
+        }
+        if let Some(service) = self.service {
+            configuration = configuration.with_service(&service);
+        }
+        if let Some(version) = self.version {
+            configuration = configuration.with_version(&version);
+        }
+        for (key, value) in &self.tags {
+            configuration = configuration.with_tag(key, value);
+        }
+        if let Some(socket_path) = self.socket_path {
+            configuration = configuration.with_socket_path(socket_path);
+        }
+        if self.udp_fallback {
+            configuration = configuration.with_udp_fallback();
+        }
+        if let Some(max_payload_size) = self.max_payload_size {
+            if max_payload_size == 0 {
+                return Err("max_payload_size must be greater than 0".to_string());
+            }
+            configuration = configuration.with_max_payload_size(max_payload_size);
+        }
+        if let Some(batching) = self.batching {
+            if batching.max_buffer_size == 0 {
+                return Err("batching.max_buffer_size must be greater than 0".to_string());
+            }
+            configuration = configuration.with_batching_options(BatchingOptions {
+                max_buffer_size: batching.max_buffer_size,
+                max_time: Duration::from_millis(batching.max_time_ms),
+                max_retry_attempts: batching.max_retry_attempts,
+                initial_retry_delay: batching.initial_retry_delay_ms,
+            });
+        }
+        if let Some(worker) = self.worker {
+            let defaults = WorkerOptions::default();
+            let queue_capacity = worker.queue_capacity.unwrap_or(defaults.queue_capacity);
+            if queue_capacity == 0 {
+                return Err("worker.queue_capacity must be greater than 0".to_string());
+            }
+            configuration = configuration.with_worker_options(WorkerOptions {
+                queue_capacity,
+                overflow_policy: worker.overflow_policy.unwrap_or(defaults.overflow_policy),
+            });
+        }
+        if let Some(aggregation) = self.aggregation {
+            let flush_interval = aggregation
+                .flush_interval_ms
+                .map(Duration::from_millis)
+                .unwrap_or(AggregationOptions::default().flush_interval);
+            if flush_interval.is_zero() {
+                return Err("aggregation.flush_interval_ms must be greater than 0".to_string());
+            }
+            let sketches = match aggregation.sketches {
+                Some(sketches) => {
+                    let defaults = SketchOptions::default();
+                    let sketches = SketchOptions {
+                        relative_accuracy: sketches.relative_accuracy.unwrap_or(defaults.relative_accuracy),
+                        percentiles: sketches.percentiles.unwrap_or(defaults.percentiles),
+                    };
+                    sketches
+                        .validate()
+                        .map_err(|error| format!("aggregation.sketches: {error}"))?;
+                    Some(sketches)
+                }
+                None => None,
+            };
+            configuration = configuration.with_aggregation(AggregationOptions {
+                flush_interval,
+                sketches,
+            });
+        }
+        match self.telemetry_interval_ms {
+            Some(0) => configuration = configuration.without_telemetry(),
+            Some(interval) => configuration = configuration.with_telemetry_interval(Duration::from_millis(interval)),
+            None => {}
+        }
+        match self.gauge_interval_ms {
+            Some(0) => return Err("gauge_interval_ms must be greater than 0".to_string()),
+            Some(interval) => configuration = configuration.with_gauge_interval(Duration::from_millis(interval)),
+            None => {}
+        }
+        if let Some(rate_limit) = self.error_rate_limit_ms {
+            configuration = configuration.with_error_rate_limit(Duration::from_millis(rate_limit));
+        }
+        for (metric, rate) in self.sample_rates {
+            if !(rate > 0.0 && rate <= 1.0) {
+                return Err(format!("sample_rates.{metric} must be greater than 0 and at most 1"));
+            }
+            configuration = configuration.with_sample_rate(&metric, rate);
+        }
+        if self.origin_detection {
+            configuration = configuration.with_origin_detection();
+        }
+        if self.validation {
+            configuration = configuration.with_validation();
+        }
+        if let Some(container_id) = self.container_id {
+            configuration = configuration.with_container_id(&container_id);
+        }
+        if let Some(timing) = self.timing {
+            configuration = configuration.with_timing_options(TimingOptions {
+                unit: timing.unit,
+                as_distribution: timing.as_distribution,
+            });
+        }
+        if let Some(tracker) = self.tracker {
+            let mut tracker_configuration = TagTrackerConfiguration::new();
+            if let Some(threshold) = tracker.threshold {
+                tracker_configuration = tracker_configuration.with_threshold(threshold);
+            }
+            for action in tracker.actions {
+                tracker_configuration = match action {
+                    ActionDef::Event { title, text } => tracker_configuration.with_event(title, text),
+                };
+            }
+            configuration = configuration.with_tracker_configuration(tracker_configuration);
+        }
+        Ok(configuration)
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'de> Deserialize<'de> for Configuration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        ConfigurationDef::deserialize(deserializer)?
+            .into_configuration()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_deserialize() {
+        let config: Configuration = serde_json::from_str(
+            r#"{
+                "to_addr": "10.0.0.1:8125",
+                "namespace": "quotes",
+                "environment": "production",
+                "country": "it",
+                "service": "quotes",
+                "version": "1.2.3",
+                "tags": {"team": "pricing", "region": "eu-west-1"},
+                "batching": {"max_buffer_size": 8000, "max_time_ms": 100, "max_retry_attempts": 3, "initial_retry_delay_ms": 10},
+                "worker": {"overflow_policy": "drop_oldest"},
+                "aggregation": {"sketches": {"percentiles": [0.9]}},
+                "telemetry_interval_ms": 0,
+                "sample_rates": {"hits": 0.5},
+                "timing": {"unit": "fractional_milliseconds"},
+                "origin_detection": true,
+                "validation": true,
+                "tracker": {"threshold": 50, "actions": [{"action": "event", "title": "title", "text": "text"}]}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.to_addr(), "10.0.0.1:8125");
+        assert_eq!(config.namespace(), "quotes");
+        // The tags read from the `KUBE_APP_*` variables depend on where the tests run
+        let tags: Vec<String> = config
+            .default_tags()
+            .into_iter()
+            .filter(|tag| !tag.starts_with("kube_app_"))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                "env:production",
+                "prima_country:it",
+                "service:quotes",
+                "version:1.2.3",
+                "region:eu-west-1",
+                "team:pricing",
+            ]
+        );
+        assert_eq!(config.batching_options().unwrap().max_time, Duration::from_millis(100));
+        assert_eq!(
+            config.worker_options(),
+            Some(WorkerOptions {
+                queue_capacity: WorkerOptions::default().queue_capacity,
+                overflow_policy: OverflowPolicy::DropOldest,
+            })
+        );
+        assert_eq!(
+            config.aggregation_options().unwrap().sketches,
+            Some(SketchOptions {
+                relative_accuracy: 0.01,
+                percentiles: vec![0.9],
+            })
+        );
+        assert_eq!(config.telemetry_interval(), None);
+        assert_eq!(config.sample_rates().get("hits"), Some(&0.5));
+        assert_eq!(config.timing_options().unit, TimingUnit::FractionalMilliseconds);
+        assert!(!config.timing_options().as_distribution);
+        assert!(config.origin_detection());
+        assert!(config.validation());
+        assert_eq!(config.container_id(), None);
+    }
+
+    #[test]
+    pub fn test_deserialize_errors() {
+        let error = |json: &str| serde_json::from_str::<Configuration>(json).err().unwrap().to_string();
+
+        assert!(error(r#"{"to_addr": "localhost:8125", "environment": "pr od"}"#)
+            .contains("invalid environment tag `pr od`"));
+        assert!(error(r#"{"to_addr": "localhost:8125", "adress": ""}"#).contains("unknown field `adress`"));
+        assert!(
+            error(r#"{"to_addr": "localhost:8125", "worker": {"queue_capacity": 0}}"#)
+                .contains("worker.queue_capacity must be greater than 0")
+        );
+        assert!(
+            error(r#"{"to_addr": "localhost:8125", "aggregation": {"sketches": {"relative_accuracy": 1.5}}}"#)
+                .contains(
+                    "aggregation.sketches: Invalid sketch options: the relative accuracy must be between 0 and 1"
+                )
+        );
+        assert!(
+            error(r#"{"to_addr": "localhost:8125", "aggregation": {"sketches": {"percentiles": [2.0]}}}"#)
+                .contains("the percentiles must be whole percents between 0 and 1")
+        );
+        assert!(
+            error(r#"{"to_addr": "localhost:8125", "tracker": {"actions": [{"action": "log"}]}}"#)
+                .contains("unknown variant `log`")
+        );
+    }
+}
@@ -2,22 +2,45 @@ use std::str::FromStr;
 
 use crate::error::Error;
 
+use super::is_valid_tag_value;
+
 /// Represent an environment in which the datadog client runs.
 /// This is useful for enforcing rules based on environment for every application that uses the library.
+///
+/// Parsing is case insensitive and accepts the `prod`, `stg` and `development` aliases. Any other
+/// valid tag value is parsed as [Environment::Custom], e.g. for preview environments.
 #[derive(PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
 pub enum Environment {
+    Local,
     Dev,
+    Qa,
     Staging,
     Production,
+    /// Any other environment. Use [Environment::custom] to check that it is a valid tag value.
+    Custom(String),
 }
 
 impl Environment {
+    /// Creates a [Environment::Custom] environment, failing if it is not a valid tag value
+    pub fn custom(environment: &str) -> Result<Self, Error> {
+        let environment = environment.to_lowercase();
+        if is_valid_tag_value(&environment) {
+            Ok(Self::Custom(environment))
+        } else {
+            Err(Error::WrongEnvironmentDefinition)
+        }
+    }
+
     /// Returns the string representation of the environment.
-    pub fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &str {
         match self {
+            Environment::Local => "local",
             Environment::Dev => "dev",
+            Environment::Qa => "qa",
             Environment::Staging => "staging",
             Environment::Production => "production",
+            Environment::Custom(environment) => environment,
         }
     }
 }
@@ -26,11 +49,13 @@ impl FromStr for Environment {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "dev" => Ok(Self::Dev),
-            "staging" => Ok(Self::Staging),
-            "production" => Ok(Self::Production),
-            _ => Err(Error::WrongEnvironmentDefinition),
+        match s.to_lowercase().as_str() {
+            "local" => Ok(Self::Local),
+            "dev" | "development" => Ok(Self::Dev),
+            "qa" => Ok(Self::Qa),
+            "staging" | "stg" => Ok(Self::Staging),
+            "production" | "prod" => Ok(Self::Production),
+            _ => Self::custom(s),
         }
     }
 }
@@ -60,13 +85,24 @@ impl<'de> serde::Deserialize<'de> for Environment {
             where
                 E: serde::de::Error,
             {
-                Environment::from_str(v).map_err(|_| E::custom("unknown environment tag"))
+                Environment::from_str(v).map_err(|_| E::custom(format!("invalid environment tag `{v}`")))
             }
         }
         deserializer.deserialize_str(EnvironmentVisitor)
     }
 }
 
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+#[cfg(feature = "serde")]
+impl serde::Serialize for Environment {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::configuration::Configuration;
@@ -78,12 +114,33 @@ mod tests {
         assert_eq!(Some(Environment::Dev), "dev".parse().ok());
         assert_eq!(Some(Environment::Staging), "staging".parse().ok());
         assert_eq!(Some(Environment::Production), "production".parse().ok());
+        assert_eq!(Some(Environment::Local), "local".parse().ok());
+        assert_eq!(Some(Environment::Qa), "qa".parse().ok());
+    }
+
+    #[test]
+    pub fn test_from_str_aliases() {
+        assert_eq!(Some(Environment::Production), "prod".parse().ok());
+        assert_eq!(Some(Environment::Production), "PRODUCTION".parse().ok());
+        assert_eq!(Some(Environment::Staging), "stg".parse().ok());
+        assert_eq!(Some(Environment::Dev), "Development".parse().ok());
+    }
+
+    #[test]
+    pub fn test_from_str_custom() {
+        assert_eq!(
+            Some(Environment::Custom("preview-1234".to_string())),
+            "Preview-1234".parse().ok()
+        );
+        assert_eq!("preview-1234", Environment::custom("preview-1234").unwrap().to_string());
     }
 
     #[test]
     pub fn test_from_str_err() {
-        //assert_eq!(None, "".parse::<Environment>().err());
-        assert_eq!(None, "whatever".parse::<Environment>().ok());
+        assert_eq!(None, "".parse::<Environment>().ok());
+        assert_eq!(None, "what ever".parse::<Environment>().ok());
+        assert_eq!(None, "what,ever".parse::<Environment>().ok());
+        assert_eq!(None, "a".repeat(201).parse::<Environment>().ok());
     }
 
     #[test]
@@ -1,15 +1,30 @@
 //! Configuration module
 
+mod aggregation;
 mod country;
+#[cfg(feature = "serde")]
+mod de;
 mod env;
+mod socket;
+mod timing;
+mod worker;
 
+pub use aggregation::{AggregationOptions, SketchOptions};
 pub use country::Country;
 pub use env::Environment;
+pub use socket::SocketType;
+pub use timing::{TimingOptions, TimingUnit};
+pub use worker::{OverflowPolicy, WorkerOptions};
 
 pub use dogstatsd::BatchingOptions;
 
-use crate::TagTrackerConfiguration;
-use std::fmt::Display;
+use crate::{error::Error, statsd::MetricType, TagTrackerConfiguration};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{atomic::AtomicU64, Arc},
+    time::Duration,
+};
 
 /// By binding to 0.0.0.0:0 we're just letting the OS assign us a port, and letting anyone send us UDP packets on that port
 ///
@@ -17,7 +32,53 @@ use std::fmt::Display;
 /// We can't just bind to a different address (e.g. localhost) because that would prevent us from sending data to the server
 const DEFAULT_FROM_ADDR: &str = "0.0.0.0:0";
 
+/// The default host and port of the agent, see [Configuration::from_env]
+const DEFAULT_AGENT_HOST: &str = "localhost";
+const DEFAULT_DOGSTATSD_PORT: &str = "8125";
+
+/// How often the client reports its telemetry, as the official Datadog clients do
+const DEFAULT_TELEMETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often the gauges registered with `Datadog::register_gauge` are polled by default
+pub(crate) const DEFAULT_GAUGE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// By default a misconfigured client reports an error once per minute
+const DEFAULT_ERROR_RATE_LIMIT: Duration = Duration::from_secs(60);
+
+/// A callback receiving the errors occurred while sending a metric, with the name and the type of
+/// the metric. See [Configuration::with_error_handler].
+pub type ErrorHandler = Arc<dyn Fn(&Error, &str, MetricType) + Send + Sync>;
+
 /// The struct that represents options for the Datadog client in Prima.
+///
+/// With the `serde` feature it can be deserialized, e.g. from a configuration file:
+///
+/// ```json
+/// {
+///     "to_addr": "127.0.0.1:8125",
+///     "namespace": "my_service",
+///     "environment": "production",
+///     "service": "my_service",
+///     "version": "1.0.0",
+///     "tags": {"team": "platform"},
+///     "socket_path": "unixstream:///var/run/datadog/dsd.socket",
+///     "udp_fallback": true,
+///     "max_payload_size": 8192,
+///     "batching": {"max_buffer_size": 8000, "max_time_ms": 100, "max_retry_attempts": 3, "initial_retry_delay_ms": 10},
+///     "worker": {"queue_capacity": 16384, "overflow_policy": "drop_newest"},
+///     "aggregation": {"flush_interval_ms": 2000, "sketches": {"relative_accuracy": 0.01, "percentiles": [0.5, 0.95]}},
+///     "telemetry_interval_ms": 10000,
+///     "gauge_interval_ms": 10000,
+///     "error_rate_limit_ms": 60000,
+///     "sample_rates": {"http.requests": 0.1},
+///     "timing": {"unit": "microseconds", "as_distribution": true},
+///     "origin_detection": true,
+///     "validation": true,
+///     "tracker": {"threshold": 100, "actions": [{"action": "event", "title": "Too many metrics", "text": "..."}]}
+/// }
+/// ```
+///
+/// Only `to_addr` is required. A `telemetry_interval_ms` of `0` disables the telemetry.
 pub struct Configuration {
     to_addr: String,
     from_addr: String,
@@ -25,7 +86,21 @@ pub struct Configuration {
     tags: Vec<String>,
     tracker: TagTrackerConfiguration,
     socket_path: Option<String>,
+    socket_type: SocketType,
+    udp_fallback: bool,
+    max_payload_size: Option<usize>,
     batching_options: Option<BatchingOptions>,
+    worker_options: Option<WorkerOptions>,
+    aggregation_options: Option<AggregationOptions>,
+    telemetry_interval: Option<Duration>,
+    gauge_interval: Duration,
+    error_handler: Option<ErrorHandler>,
+    error_rate_limit: Duration,
+    sample_rates: HashMap<String, f64>,
+    timing_options: TimingOptions,
+    origin_detection: bool,
+    container_id: Option<String>,
+    validation: bool,
 }
 
 impl Configuration {
@@ -34,11 +109,96 @@ impl Configuration {
             to_addr: to_addr.to_string(),
             from_addr: DEFAULT_FROM_ADDR.to_string(),
             namespace: namespace.to_string(),
-            tags: get_env_tags(),
+            tags: get_env_tags(&|name| std::env::var(name).ok()),
             tracker: TagTrackerConfiguration::new(),
             socket_path: None,
+            socket_type: SocketType::Datagram,
+            udp_fallback: false,
+            max_payload_size: None,
             batching_options: None,
+            worker_options: None,
+            aggregation_options: None,
+            telemetry_interval: Some(DEFAULT_TELEMETRY_INTERVAL),
+            gauge_interval: DEFAULT_GAUGE_INTERVAL,
+            error_handler: None,
+            error_rate_limit: DEFAULT_ERROR_RATE_LIMIT,
+            sample_rates: HashMap::new(),
+            timing_options: TimingOptions::default(),
+            origin_detection: false,
+            container_id: None,
+            validation: false,
+        }
+    }
+
+    /// Creates a configuration from the environment variables used by the official Datadog clients,
+    /// so that every service can be configured the same way:
+    ///
+    /// - `DD_DOGSTATSD_URL`, either `udp://host:port`, `unix:///path/to/dsd.socket` or
+    ///   `unixstream:///path/to/dsd.socket`
+    /// - `DD_DOGSTATSD_SOCKET`, the path of the unix socket of the agent
+    /// - `DD_AGENT_HOST` and `DD_DOGSTATSD_PORT`, defaulting to `localhost` and `8125`
+    /// - `DD_ENV`, `DD_SERVICE` and `DD_VERSION`, added as `env`, `service` and `version` tags
+    /// - `DD_TAGS`, a list of tags separated by commas or spaces
+    ///
+    /// The address is taken from the first of `DD_DOGSTATSD_URL`, `DD_DOGSTATSD_SOCKET` and
+    /// `DD_AGENT_HOST` which is set. The namespace is empty, and can be set with
+    /// [Configuration::with_namespace].
+    ///
+    /// ```
+    /// use prima_datadog::configuration::Configuration;
+    ///
+    /// let configuration = Configuration::from_env().unwrap().with_namespace("my_service");
+    /// ```
+    pub fn from_env() -> Result<Self, Error> {
+        Self::from_env_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_env_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, Error> {
+        let var = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+        let agent_addr = || {
+            let host = var("DD_AGENT_HOST").unwrap_or_else(|| DEFAULT_AGENT_HOST.to_string());
+            let port = var("DD_DOGSTATSD_PORT").unwrap_or_else(|| DEFAULT_DOGSTATSD_PORT.to_string());
+            // IPv6 addresses must be enclosed in brackets to be joined with the port
+            if host.contains(':') && !host.starts_with('[') {
+                format!("[{host}]:{port}")
+            } else {
+                format!("{host}:{port}")
+            }
+        };
+
+        let mut configuration = match (var("DD_DOGSTATSD_URL"), var("DD_DOGSTATSD_SOCKET")) {
+            (Some(url), _) => {
+                if let Some(addr) = url.strip_prefix("udp://") {
+                    Self::new(addr, "")
+                } else if url.starts_with("unix://") || url.starts_with("unixstream://") {
+                    Self::new(&agent_addr(), "").with_socket_path(url)
+                } else {
+                    return Err(Error::WrongDogstatsdUrlDefinition(url));
+                }
+            }
+            (None, Some(path)) => Self::new(&agent_addr(), "").with_socket_path(path),
+            (None, None) => Self::new(&agent_addr(), ""),
+        };
+
+        configuration.tags = get_env_tags(&var);
+        for (name, key) in [("DD_ENV", "env"), ("DD_SERVICE", "service"), ("DD_VERSION", "version")] {
+            if let Some(value) = var(name) {
+                configuration.tags.push(format!("{key}:{value}"));
+            }
+        }
+        if let Some(tags) = var("DD_TAGS") {
+            configuration.tags.extend(
+                tags.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string),
+            );
         }
+        Ok(configuration)
+    }
+
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace.to_string();
+        self
     }
 
     pub fn with_from_addr(mut self, from_addr: &str) -> Self {
@@ -51,6 +211,32 @@ impl Configuration {
         self
     }
 
+    /// Adds the `service` tag of [unified service tagging](https://docs.datadoghq.com/getting_started/tagging/unified_service_tagging/)
+    pub fn with_service(mut self, service: &str) -> Self {
+        self.tags.push(format!("service:{service}"));
+        self
+    }
+
+    /// Adds the `version` tag of unified service tagging. The version of the calling crate can be
+    /// used with the [pkg_version](crate::pkg_version) macro:
+    ///
+    /// ```
+    /// use prima_datadog::{configuration::Configuration, pkg_version};
+    ///
+    /// let configuration = Configuration::new("0.0.0.0:1234", "namespace").with_version(pkg_version!());
+    /// ```
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.tags.push(format!("version:{version}"));
+        self
+    }
+
+    /// Makes [Datadog::init](crate::Datadog::init) fail if the `service` or `version` tag is
+    /// missing in production, see [Configuration::validate]. Disabled by default.
+    pub fn with_validation(mut self) -> Self {
+        self.validation = true;
+        self
+    }
+
     pub fn with_tag<T: Display>(mut self, key: &str, value: &T) -> Self {
         self.tags.push(format!("{key}:{value}"));
         self
@@ -65,16 +251,149 @@ impl Configuration {
         self
     }
 
+    /// Sends the metrics over the unix socket of the agent instead of UDP. The path can be prefixed
+    /// by `unix://` for a datagram socket (the default) or by `unixstream://` for a stream socket,
+    /// see [SocketType].
+    ///
+    /// The socket doesn't need to exist when the client is created: the metrics sent while the
+    /// agent is not listening are dropped, and the client reconnects once it is back, e.g. after a
+    /// restart.
     pub fn with_socket_path(mut self, socket_path: String) -> Self {
-        self.socket_path = Some(socket_path);
+        let (socket_type, path) = SocketType::parse(&socket_path);
+        self.socket_type = socket_type;
+        self.socket_path = Some(path.to_string());
         self
     }
 
+    /// Sends the metrics over UDP to `to_addr` if the socket path doesn't exist when the client is
+    /// created, e.g. when the same configuration is used in environments where the agent only
+    /// listens on UDP
+    pub fn with_udp_fallback(mut self) -> Self {
+        self.udp_fallback = true;
+        self
+    }
+
+    /// Sets the maximum size of a single packet sent to the agent. It defaults to 1432 bytes over
+    /// UDP, which fits the usual MTU of 1500 bytes, and to 8192 bytes over a unix socket, the
+    /// default buffer size of the agent.
+    ///
+    /// The batches are split on line boundaries to fit in a packet, while a single metric, event
+    /// or service check which can never fit is dropped and counted in
+    /// [ClientStats::oversized_payloads](crate::ClientStats::oversized_payloads).
+    pub fn with_max_payload_size(mut self, max_payload_size: usize) -> Self {
+        self.max_payload_size = Some(max_payload_size);
+        self
+    }
+
+    /// Batches the metrics in a buffer of at most `max_buffer_size` bytes, which is capped by the
+    /// maximum payload size of the transport, see [Configuration::with_max_payload_size].
     pub fn with_batching_options(mut self, batching_options: BatchingOptions) -> Self {
         self.batching_options = Some(batching_options);
         self
     }
 
+    /// Emits the metrics through a background worker: the metric functions only push the metric in
+    /// a bounded queue, while the worker thread serializes and sends them.
+    ///
+    /// The worker is always used when batching is enabled, with [WorkerOptions::default] unless
+    /// configured otherwise.
+    pub fn with_worker_options(mut self, worker_options: WorkerOptions) -> Self {
+        self.worker_options = Some(worker_options);
+        self
+    }
+
+    /// Aggregates the counts, gauges and sets on the client side, sending a single value per metric
+    /// and tags every [AggregationOptions::flush_interval], and optionally the histograms and
+    /// distributions as well, see [AggregationOptions::sketches] and
+    /// [AggregatingClient](crate::aggregation::AggregatingClient).
+    pub fn with_aggregation(mut self, aggregation_options: AggregationOptions) -> Self {
+        self.aggregation_options = Some(aggregation_options);
+        self
+    }
+
+    /// Sets how often the client reports its own `datadog.dogstatsd.client.*` metrics (10 seconds
+    /// by default).
+    pub fn with_telemetry_interval(mut self, interval: Duration) -> Self {
+        self.telemetry_interval = Some(interval);
+        self
+    }
+
+    /// Disables the `datadog.dogstatsd.client.*` metrics reported by the client. The counters are
+    /// still available through [Datadog::stats](crate::Datadog::stats).
+    pub fn without_telemetry(mut self) -> Self {
+        self.telemetry_interval = None;
+        self
+    }
+
+    /// Sets how often the gauges registered with [Datadog::register_gauge](crate::Datadog::register_gauge)
+    /// are polled, 10 seconds by default. Intervals shorter than 10 milliseconds are raised to 10
+    /// milliseconds.
+    pub fn with_gauge_interval(mut self, interval: Duration) -> Self {
+        self.gauge_interval = interval;
+        self
+    }
+
+    /// Sets a callback receiving the errors occurred while sending the metrics to the agent, with
+    /// the name (without the namespace) and the type of the metric, or of the first metric of the
+    /// batch if batching is enabled. Errors are ignored by default.
+    ///
+    /// The handler is called at most once per minute, see [Configuration::with_error_rate_limit].
+    ///
+    /// ```
+    /// use prima_datadog::configuration::Configuration;
+    ///
+    /// let configuration = Configuration::new("0.0.0.0:1234", "namespace").with_error_handler(|error, metric, metric_type| {
+    ///     eprintln!("Unable to send the {metric_type:?} {metric} to Datadog: {error}");
+    /// });
+    /// ```
+    pub fn with_error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Error, &str, MetricType) + Send + Sync + 'static,
+    {
+        self.error_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Sets the minimum interval between two calls to the error handler. The errors occurred in
+    /// between are ignored.
+    pub fn with_error_rate_limit(mut self, interval: Duration) -> Self {
+        self.error_rate_limit = interval;
+        self
+    }
+
+    /// Samples every count, timing, histogram and distribution named `metric` (without the
+    /// namespace) at `rate`, unless it is emitted with an explicit rate, e.g. with
+    /// [Datadog::incr_with_rate](crate::Datadog::incr_with_rate)
+    pub fn with_sample_rate(mut self, metric: &str, rate: f64) -> Self {
+        self.sample_rates.insert(metric.to_string(), rate);
+        self
+    }
+
+    /// Sets the unit of the timings measured by the library, and whether they are sent as
+    /// distributions. By default they are sent as timers in whole milliseconds.
+    pub fn with_timing_options(mut self, timing_options: TimingOptions) -> Self {
+        self.timing_options = timing_options;
+        self
+    }
+
+    /// Detects the ID of the container the process runs in, from `/proc/self/cgroup` or
+    /// `/proc/self/mountinfo`, and sends it with every metric, event and service check so that the
+    /// agent can tag them with the pod and container they come from. The detection is skipped when
+    /// `DD_ENTITY_ID` is set, since the agent can already find the origin from it.
+    ///
+    /// See <https://docs.datadoghq.com/developers/dogstatsd/?tab=kubernetes#origin-detection-over-udp>
+    pub fn with_origin_detection(mut self) -> Self {
+        self.origin_detection = true;
+        self
+    }
+
+    /// Sends the given container ID with every metric, event and service check instead of
+    /// detecting it, see [Configuration::with_origin_detection]
+    pub fn with_container_id(mut self, container_id: &str) -> Self {
+        self.container_id = Some(container_id.to_string());
+        self
+    }
+
     pub fn to_addr(&self) -> &str {
         self.to_addr.as_str()
     }
@@ -95,13 +414,122 @@ impl Configuration {
         self.socket_path.clone()
     }
 
+    pub fn socket_type(&self) -> SocketType {
+        self.socket_type
+    }
+
+    pub fn udp_fallback(&self) -> bool {
+        self.udp_fallback
+    }
+
+    pub fn max_payload_size(&self) -> Option<usize> {
+        self.max_payload_size
+    }
+
     pub fn batching_options(&self) -> Option<BatchingOptions> {
         self.batching_options
     }
 
+    pub fn worker_options(&self) -> Option<WorkerOptions> {
+        self.worker_options
+    }
+
+    pub fn aggregation_options(&self) -> Option<AggregationOptions> {
+        self.aggregation_options.clone()
+    }
+
+    pub fn telemetry_interval(&self) -> Option<Duration> {
+        self.telemetry_interval
+    }
+
+    pub fn gauge_interval(&self) -> Duration {
+        self.gauge_interval
+    }
+
+    pub fn error_handler(&self) -> Option<ErrorHandler> {
+        self.error_handler.clone()
+    }
+
+    pub fn error_rate_limit(&self) -> Duration {
+        self.error_rate_limit
+    }
+
+    /// Checks that the `service` and `version` tags are set when running in the production
+    /// environment, as required by unified service tagging.
+    ///
+    /// The environment is production if the value of an `env` tag parses as
+    /// [Environment::Production], i.e. `production` or `prod` in any case, whether it is set by
+    /// [Configuration::with_environment], by a custom tag or by `DD_ENV` with
+    /// [Configuration::from_env].
+    ///
+    /// This is called by [Datadog::init](crate::Datadog::init) only when enabled with
+    /// [Configuration::with_validation].
+    pub fn validate(&self) -> Result<(), Error> {
+        let tags = self.resolved_tags();
+        let has_tag = |key: &str| {
+            tags.iter()
+                .any(|tag| tag.strip_prefix(key).is_some_and(|t| t.starts_with(':')))
+        };
+        let production = tags
+            .iter()
+            .filter_map(|tag| tag.strip_prefix("env:"))
+            .any(|env| env.parse::<Environment>().ok() == Some(Environment::Production));
+        if !production {
+            return Ok(());
+        }
+        let missing: Vec<&'static str> = ["service", "version"]
+            .iter()
+            .copied()
+            .filter(|key| !has_tag(key))
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::MissingUnifiedServiceTags(missing))
+        }
+    }
+
+    /// The default tags, plus the `dd.internal.entity_id` tag of origin detection taken from
+    /// `DD_ENTITY_ID` unless already configured
+    pub(crate) fn resolved_tags(&self) -> Vec<String> {
+        let mut tags = self.tags.clone();
+        let prefix = "dd.internal.entity_id:";
+        if !tags.iter().any(|tag| tag.starts_with(prefix)) {
+            if let Ok(entity_id) = std::env::var("DD_ENTITY_ID") {
+                tags.push(format!("{prefix}{entity_id}"));
+            }
+        }
+        tags
+    }
+
+    pub fn sample_rates(&self) -> &HashMap<String, f64> {
+        &self.sample_rates
+    }
+
+    pub fn timing_options(&self) -> TimingOptions {
+        self.timing_options
+    }
+
+    pub fn origin_detection(&self) -> bool {
+        self.origin_detection
+    }
+
+    pub fn validation(&self) -> bool {
+        self.validation
+    }
+
+    pub fn container_id(&self) -> Option<&str> {
+        self.container_id.as_deref()
+    }
+
     pub fn take_tracker_config(&mut self) -> TagTrackerConfiguration {
         std::mem::replace(&mut self.tracker, TagTrackerConfiguration::new())
     }
+
+    /// The number of actions taken by the tag tracker, reported in the telemetry of the client
+    pub(crate) fn tracker_events(&self) -> Arc<AtomicU64> {
+        self.tracker.actions_fired()
+    }
 }
 
 impl From<Configuration> for dogstatsd::Options {
@@ -117,18 +545,28 @@ impl From<Configuration> for dogstatsd::Options {
     }
 }
 
-fn get_env_tags() -> Vec<String> {
+/// Whether the value can be used in a tag: at most 200 lowercase letters, digits, `_`, `-`, `:`, `.`
+/// and `/`. See <https://docs.datadoghq.com/getting_started/tagging/#define-tags>
+pub(crate) fn is_valid_tag_value(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 200
+        && value
+            .chars()
+            .all(|c| c.is_lowercase() || c.is_numeric() || matches!(c, '_' | '-' | ':' | '.' | '/'))
+}
+
+fn get_env_tags(var: &dyn Fn(&str) -> Option<String>) -> Vec<String> {
     let mut tags = vec![];
-    if let Ok(part_of) = std::env::var("KUBE_APP_PART_OF") {
+    if let Some(part_of) = var("KUBE_APP_PART_OF") {
         tags.push(format!("kube_app_part_of:{part_of}"));
     }
-    if let Ok(managed_by) = std::env::var("KUBE_APP_MANAGED_BY") {
+    if let Some(managed_by) = var("KUBE_APP_MANAGED_BY") {
         tags.push(format!("kube_app_managed_by:{managed_by}"));
     }
-    if let Ok(version) = std::env::var("KUBE_APP_VERSION") {
+    if let Some(version) = var("KUBE_APP_VERSION") {
         tags.push(format!("kube_app_version:{version}"));
     }
-    if let Ok(instance) = std::env::var("KUBE_APP_INSTANCE") {
+    if let Some(instance) = var("KUBE_APP_INSTANCE") {
         tags.push(format!("kube_app_instance:{instance}"));
     }
     tags
@@ -148,4 +586,132 @@ mod tests {
 
         assert_eq!(config.default_tags(), vec!["key:value", "count:1"]);
     }
+
+    #[test]
+    pub fn test_unified_service_tags() {
+        let config = Configuration::new("to_addr", "namespace")
+            .with_environment(Environment::Production)
+            .with_service("quotes")
+            .with_version("1.2.3");
+
+        assert_eq!(
+            config.default_tags(),
+            vec!["env:production", "service:quotes", "version:1.2.3"]
+        );
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    pub fn test_validate() {
+        let config = Configuration::new("to_addr", "namespace").with_environment(Environment::Staging);
+        assert!(config.validate().is_ok());
+        assert!(!config.validation());
+
+        for env in ["prod", "PRODUCTION"] {
+            let config = Configuration::new("to_addr", "namespace").with_tag("env", &env);
+            assert!(matches!(
+                config.validate(),
+                Err(Error::MissingUnifiedServiceTags(missing)) if missing == ["service", "version"]
+            ));
+        }
+
+        let config = Configuration::new("to_addr", "namespace")
+            .with_environment(Environment::Production)
+            .with_tag("service_name", &"quotes");
+        assert!(matches!(
+            config.validate(),
+            Err(Error::MissingUnifiedServiceTags(missing)) if missing == ["service", "version"]
+        ));
+
+        let config = config.with_service("quotes");
+        assert!(matches!(
+            config.validate(),
+            Err(Error::MissingUnifiedServiceTags(missing)) if missing == ["version"]
+        ));
+    }
+
+    fn from_env_vars(vars: &[(&str, &str)]) -> Result<Configuration, Error> {
+        Configuration::from_env_vars(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    pub fn test_from_env_defaults() {
+        let config = from_env_vars(&[]).unwrap();
+
+        assert_eq!(config.to_addr(), "localhost:8125");
+        assert_eq!(config.socket_path(), None);
+        assert_eq!(config.namespace(), "");
+        assert!(config.default_tags().is_empty());
+    }
+
+    #[test]
+    pub fn test_from_env_agent_host() {
+        let config = from_env_vars(&[("DD_AGENT_HOST", "10.0.0.1"), ("DD_DOGSTATSD_PORT", "9125")]).unwrap();
+        assert_eq!(config.to_addr(), "10.0.0.1:9125");
+
+        let config = from_env_vars(&[("DD_AGENT_HOST", "::1")]).unwrap();
+        assert_eq!(config.to_addr(), "[::1]:8125");
+    }
+
+    #[test]
+    pub fn test_from_env_url() {
+        let config = from_env_vars(&[("DD_DOGSTATSD_URL", "udp://agent:8126"), ("DD_AGENT_HOST", "ignored")]).unwrap();
+        assert_eq!(config.to_addr(), "agent:8126");
+        assert_eq!(config.socket_path(), None);
+
+        let config = from_env_vars(&[
+            ("DD_DOGSTATSD_URL", "unix:///var/run/datadog/dsd.socket"),
+            ("DD_DOGSTATSD_SOCKET", "/ignored.socket"),
+        ])
+        .unwrap();
+        assert_eq!(config.socket_path().as_deref(), Some("/var/run/datadog/dsd.socket"));
+        assert_eq!(config.socket_type(), SocketType::Datagram);
+
+        let config = from_env_vars(&[("DD_DOGSTATSD_URL", "unixstream:///var/run/datadog/dsd.socket")]).unwrap();
+        assert_eq!(config.socket_path().as_deref(), Some("/var/run/datadog/dsd.socket"));
+        assert_eq!(config.socket_type(), SocketType::Stream);
+
+        assert!(matches!(
+            from_env_vars(&[("DD_DOGSTATSD_URL", "tcp://agent:8125")]),
+            Err(Error::WrongDogstatsdUrlDefinition(url)) if url == "tcp://agent:8125"
+        ));
+    }
+
+    #[test]
+    pub fn test_from_env_socket() {
+        let config = from_env_vars(&[("DD_DOGSTATSD_SOCKET", "/var/run/datadog/dsd.socket")]).unwrap();
+
+        assert_eq!(config.socket_path().as_deref(), Some("/var/run/datadog/dsd.socket"));
+    }
+
+    #[test]
+    pub fn test_from_env_tags() {
+        let config = from_env_vars(&[
+            ("DD_ENV", "production"),
+            ("DD_SERVICE", "quotes"),
+            ("DD_VERSION", "1.2.3"),
+            ("DD_TAGS", "team:pricing, region:eu-west-1 tier:1"),
+            ("KUBE_APP_PART_OF", "platform"),
+        ])
+        .unwrap()
+        .with_namespace("quotes");
+
+        assert_eq!(config.namespace(), "quotes");
+        assert_eq!(
+            config.default_tags(),
+            vec![
+                "kube_app_part_of:platform",
+                "env:production",
+                "service:quotes",
+                "version:1.2.3",
+                "team:pricing",
+                "region:eu-west-1",
+                "tier:1",
+            ]
+        );
+    }
 }
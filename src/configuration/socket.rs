@@ -0,0 +1,46 @@
+/// How the metrics are sent over the unix socket of the agent, see
+/// [Configuration::with_socket_path](super::Configuration::with_socket_path).
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum SocketType {
+    /// One datagram per payload, `unix://` (the default)
+    #[default]
+    Datagram,
+    /// A stream connection where every payload is prefixed by its length, as a 32 bits little
+    /// endian integer, `unixstream://`. It requires the agent to listen with
+    /// `dogstatsd_stream_socket`.
+    Stream,
+}
+
+impl SocketType {
+    /// Splits the `unix://` or `unixstream://` scheme of a socket path, if any
+    pub(crate) fn parse(socket_path: &str) -> (SocketType, &str) {
+        if let Some(path) = socket_path.strip_prefix("unixstream://") {
+            (SocketType::Stream, path)
+        } else if let Some(path) = socket_path.strip_prefix("unix://") {
+            (SocketType::Datagram, path)
+        } else {
+            (SocketType::Datagram, socket_path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            SocketType::parse("/var/run/datadog/dsd.socket"),
+            (SocketType::Datagram, "/var/run/datadog/dsd.socket")
+        );
+        assert_eq!(
+            SocketType::parse("unix:///var/run/datadog/dsd.socket"),
+            (SocketType::Datagram, "/var/run/datadog/dsd.socket")
+        );
+        assert_eq!(
+            SocketType::parse("unixstream:///var/run/datadog/dsd.socket"),
+            (SocketType::Stream, "/var/run/datadog/dsd.socket")
+        );
+    }
+}
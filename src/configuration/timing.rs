@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use crate::value::{self, ValueBuffer};
+
+/// The unit of the timings measured by the library, e.g. by `time!` and [Datadog::timing_duration](crate::Datadog::timing_duration).
+///
+/// The agent assumes that the `ms` timers are in milliseconds, so the timers in microseconds or
+/// nanoseconds get the unit appended to their name, e.g. `request.duration.us`. The distributions
+/// keep their name: use a metric name or a metadata making the unit clear.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum TimingUnit {
+    /// Whole milliseconds, as sent by `timing`
+    #[default]
+    Milliseconds,
+    /// Milliseconds with a decimal part, e.g. `0.25`
+    FractionalMilliseconds,
+    /// Whole microseconds, sent as `metric.us` timers
+    Microseconds,
+    /// Whole nanoseconds, sent as `metric.ns` timers
+    Nanoseconds,
+}
+
+impl TimingUnit {
+    /// Formats the duration in this unit
+    pub(crate) fn format(self, duration: Duration, buffer: &mut ValueBuffer) -> Option<&str> {
+        match self {
+            TimingUnit::Milliseconds => buffer.format(format_args!("{}", duration.as_millis())),
+            TimingUnit::FractionalMilliseconds => value::format_millis(duration, buffer),
+            TimingUnit::Microseconds => buffer.format(format_args!("{}", duration.as_micros())),
+            TimingUnit::Nanoseconds => buffer.format(format_args!("{}", duration.as_nanos())),
+        }
+    }
+
+    /// The suffix of the name of the `ms` timers in this unit, which would otherwise be read as
+    /// milliseconds
+    pub(crate) fn timer_suffix(self) -> Option<&'static str> {
+        match self {
+            TimingUnit::Milliseconds | TimingUnit::FractionalMilliseconds => None,
+            TimingUnit::Microseconds => Some("us"),
+            TimingUnit::Nanoseconds => Some("ns"),
+        }
+    }
+}
+
+/// How the timings measured by the library are sent.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct TimingOptions {
+    pub unit: TimingUnit,
+    /// Send the timings as distributions instead of `ms` timers, to get globally accurate
+    /// percentiles. The distributions keep the metric name whatever the unit, see [TimingUnit].
+    pub as_distribution: bool,
+}
+
+impl TimingOptions {
+    /// Whether the timings are sent as whole milliseconds timers, which every client supports
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let duration = Duration::from_micros(1_250);
+        let format = |unit: TimingUnit| unit.format(duration, &mut ValueBuffer::new()).map(str::to_string);
+
+        assert_eq!(format(TimingUnit::Milliseconds).as_deref(), Some("1"));
+        assert_eq!(format(TimingUnit::FractionalMilliseconds).as_deref(), Some("1.25"));
+        assert_eq!(format(TimingUnit::Microseconds).as_deref(), Some("1250"));
+        assert_eq!(format(TimingUnit::Nanoseconds).as_deref(), Some("1250000"));
+
+        let duration = Duration::from_micros(1_100);
+        assert_eq!(
+            TimingUnit::FractionalMilliseconds.format(duration, &mut ValueBuffer::new()),
+            Some("1.1")
+        );
+    }
+}
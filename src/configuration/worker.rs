@@ -0,0 +1,31 @@
+/// What to do when a metric is emitted while the queue of the background worker is full.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum OverflowPolicy {
+    /// Drop the metric being emitted
+    DropNewest,
+    /// Drop the oldest metric in the queue to make room for the one being emitted
+    DropOldest,
+    /// Block the caller until there is room in the queue. The metrics emitted by the error handler,
+    /// which runs on the worker thread, are dropped instead, since only the worker makes room.
+    Block,
+}
+
+/// Options of the background worker, which serializes and sends the metrics so that emitting a
+/// metric only enqueues it.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct WorkerOptions {
+    /// The maximum number of metrics waiting to be sent
+    pub queue_capacity: usize,
+    /// What to do when the queue is full
+    pub overflow_policy: OverflowPolicy,
+}
+
+impl Default for WorkerOptions {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 16_384,
+            overflow_policy: OverflowPolicy::DropNewest,
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Contextual tags, automatically appended to every metric emitted inside a scope.
+//!
+//! Tags like `tenant`, `route` or `job` are usually known once at the beginning of a request or a
+//! job, and should be attached to every metric emitted while handling it. Instead of repeating them
+//! at each call site, they can be set for the duration of a closure with [with_tags], or for the
+//! whole execution of a future with [ContextFutureExt::with_tags].
+//!
+//! ```
+//! use prima_datadog::{context, incr};
+//!
+//! context::with_tags(&["route:/quote"], || {
+//!     // Sent with the `route:/quote` tag
+//!     incr!("requests");
+//! });
+//! ```
+//!
+//! Scopes can be nested, in which case the tags of every enclosing scope are appended. The tags
+//! are stored in a thread local, so they are not propagated to other threads; a spawned task
+//! should be wrapped with [ContextFutureExt::with_tags] to carry its tags across the threads it is
+//! polled on.
+
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+
+thread_local! {
+    static CONTEXT_TAGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs `f` with the given tags appended to every metric emitted on the current thread while it
+/// runs, including those emitted by [TimingGuard](crate::timing_guard::TimingGuard) drops and `time!`.
+pub fn with_tags<S: AsRef<str>, R>(tags: &[S], f: impl FnOnce() -> R) -> R {
+    let _guard = ContextGuard::enter(tags);
+    f()
+}
+
+/// Returns the contextual tags currently set on this thread, from the outermost scope to the
+/// innermost one.
+pub fn current_tags() -> Vec<String> {
+    CONTEXT_TAGS.with(|tags| tags.borrow().clone())
+}
+
+/// Restores the previous contextual tags when dropped, even if the scope panics
+struct ContextGuard {
+    previous_len: usize,
+}
+
+impl ContextGuard {
+    fn enter<S: AsRef<str>>(tags: &[S]) -> Self {
+        CONTEXT_TAGS.with(|context| {
+            let mut context = context.borrow_mut();
+            let previous_len = context.len();
+            context.extend(tags.iter().map(|tag| tag.as_ref().to_string()));
+            Self { previous_len }
+        })
+    }
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        CONTEXT_TAGS.with(|context| context.borrow_mut().truncate(self.previous_len));
+    }
+}
+
+/// Extension trait to attach contextual tags to a future.
+pub trait ContextFutureExt: Future + Sized {
+    /// Appends the given tags to every metric emitted while this future is polled, whatever the
+    /// thread it is polled on.
+    ///
+    /// ```
+    /// use prima_datadog::{context::ContextFutureExt, incr};
+    ///
+    /// let task = async {
+    ///     // Sent with the `job:billing` tag
+    ///     incr!("jobs");
+    /// }
+    /// .with_tags(["job:billing"]);
+    /// ```
+    fn with_tags<S: AsRef<str>>(self, tags: impl AsRef<[S]>) -> WithTags<Self> {
+        WithTags {
+            inner: self,
+            tags: tags.as_ref().iter().map(|tag| tag.as_ref().to_string()).collect(),
+        }
+    }
+}
+
+impl<F: Future> ContextFutureExt for F {}
+
+pin_project! {
+    /// A future with contextual tags, see [ContextFutureExt::with_tags]
+    #[derive(Debug)]
+    #[must_use = "futures do nothing unless polled"]
+    pub struct WithTags<F> {
+        #[pin]
+        inner: F,
+        tags: Vec<String>,
+    }
+}
+
+impl<F: Future> Future for WithTags<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _guard = ContextGuard::enter(this.tags);
+        this.inner.poll(cx)
+    }
+}
@@ -8,14 +8,22 @@ use thiserror::Error;
 pub enum Error {
     #[error("Unable to initialize Datadog global service with once_cell, because the service was already initialized. You should call Datadog::init() just once.")]
     OnceCellAlreadyInitialized,
-    #[error(
-        "Unable to initialize environment type. The accepted values are 'local', 'qa', 'staging' and 'production'"
-    )]
+    #[error("Unable to initialize environment type. The value must be a valid tag value: lowercase letters, digits, '_', '-', ':', '.' and '/'")]
     WrongEnvironmentDefinition,
     #[error(transparent)]
     DogstatsdError(#[from] DogstatsdError),
-    #[error("Unable to initialize country type. The accepted values are 'it', 'es' and 'uk'")]
+    #[error("Unable to initialize country type. The value must be a valid tag value: lowercase letters, digits, '_', '-', ':', '.' and '/'")]
     WrongCountryDefinition,
+    #[error("Timed out while waiting for the Datadog client to send the pending metrics")]
+    ShutdownTimeout,
+    #[error(
+        "Unable to parse DD_DOGSTATSD_URL '{0}'. The accepted schemes are 'udp://', 'unix://' and 'unixstream://'"
+    )]
+    WrongDogstatsdUrlDefinition(String),
+    #[error("Missing unified service tags in production: {}", .0.join(", "))]
+    MissingUnifiedServiceTags(Vec<&'static str>),
+    #[error("Invalid sketch options: {0}")]
+    InvalidSketchOptions(&'static str),
 }
 
 #[cfg(test)]
@@ -26,6 +34,10 @@ impl Error {
             Error::WrongEnvironmentDefinition => false,
             Error::DogstatsdError(_) => false,
             Error::WrongCountryDefinition => false,
+            Error::ShutdownTimeout => false,
+            Error::WrongDogstatsdUrlDefinition(_) => false,
+            Error::MissingUnifiedServiceTags(_) => false,
+            Error::InvalidSketchOptions(_) => false,
         }
     }
 }
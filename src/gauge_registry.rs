@@ -0,0 +1,261 @@
+//! Gauges polled on an interval, see [Datadog::register_gauge]
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{Datadog, DogstatsdClient, MetricValue};
+
+/// The shortest interval the gauges are polled at, so that the polling thread never spins
+pub(crate) const MIN_GAUGE_INTERVAL: Duration = Duration::from_millis(10);
+
+type Callback = Box<dyn Fn() -> Box<dyn MetricValue> + Send + Sync>;
+
+/// The gauges registered on an instance and its clones, polled by a thread per gauge interval
+#[derive(Default)]
+pub(crate) struct GaugeRegistry {
+    state: Mutex<State>,
+    next_id: AtomicU64,
+    /// The callbacks which panicked, see [ClientStats::gauge_callback_panics](crate::ClientStats)
+    panics: AtomicU64,
+}
+
+#[derive(Default)]
+struct State {
+    gauges: Vec<Arc<Gauge>>,
+    /// The intervals whose polling thread is running, each one stops once no gauge of its interval
+    /// is registered
+    polling: Vec<Duration>,
+}
+
+struct Gauge {
+    id: u64,
+    interval: Duration,
+    metric: String,
+    tags: Vec<String>,
+    callback: Callback,
+}
+
+impl GaugeRegistry {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub(crate) fn panics(&self) -> u64 {
+        self.panics.load(Ordering::Relaxed)
+    }
+
+    /// Registers the gauge, returning whether the polling thread of its interval has to be started
+    fn register(&self, metric: String, tags: Vec<String>, interval: Duration, callback: Callback) -> (u64, bool) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut state = self.lock();
+        state.gauges.push(Arc::new(Gauge {
+            id,
+            interval,
+            metric,
+            tags,
+            callback,
+        }));
+        let start_polling = !state.polling.contains(&interval);
+        if start_polling {
+            state.polling.push(interval);
+        }
+        (id, start_polling)
+    }
+
+    fn deregister(&self, id: u64) {
+        self.lock().gauges.retain(|gauge| gauge.id != id);
+    }
+
+    /// The gauges registered with `interval`, or `None` once there is none left, stopping the
+    /// polling thread of the interval
+    fn poll(&self, interval: Duration) -> Option<Vec<Arc<Gauge>>> {
+        let mut state = self.lock();
+        let gauges: Vec<_> = state
+            .gauges
+            .iter()
+            .filter(|gauge| gauge.interval == interval)
+            .cloned()
+            .collect();
+        if gauges.is_empty() {
+            state.polling.retain(|polling| *polling != interval);
+            None
+        } else {
+            Some(gauges)
+        }
+    }
+}
+
+/// A gauge registered with [Datadog::register_gauge], which is polled until the handle is dropped
+/// or [GaugeHandle::deregister] is called.
+#[must_use = "the gauge is deregistered as soon as the handle is dropped"]
+pub struct GaugeHandle {
+    registry: Weak<GaugeRegistry>,
+    id: u64,
+}
+
+impl GaugeHandle {
+    /// A handle which doesn't refer to any gauge, returned when the global instance is not
+    /// initialized
+    pub(crate) fn inert() -> Self {
+        Self {
+            registry: Weak::new(),
+            id: 0,
+        }
+    }
+
+    /// Stops polling the gauge
+    pub fn deregister(self) {}
+}
+
+impl Drop for GaugeHandle {
+    fn drop(&mut self) {
+        if let Some(registry) = self.registry.upgrade() {
+            registry.deregister(self.id);
+        }
+    }
+}
+
+impl<C: DogstatsdClient + 'static> Datadog<C> {
+    /// Registers a gauge whose callback is polled every gauge interval of this instance, see
+    /// [Datadog::register_gauge]
+    pub(crate) fn register_gauge_callback(&self, metric: String, tags: Vec<String>, callback: Callback) -> GaugeHandle {
+        let (id, start_polling) = self.gauges.register(metric, tags, self.gauge_interval, callback);
+        if start_polling {
+            spawn_poller(self.clone(), self.gauge_interval);
+        }
+        GaugeHandle {
+            registry: Arc::downgrade(&self.gauges),
+            id,
+        }
+    }
+}
+
+/// Sends the value of every gauge registered with `interval` every `interval`, until no such gauge
+/// is registered
+fn spawn_poller<C: DogstatsdClient + 'static>(datadog: Datadog<C>, interval: Duration) {
+    let _ = thread::Builder::new()
+        .name("prima-datadog-gauges".to_string())
+        .spawn(move || loop {
+            thread::sleep(interval);
+            if !send_gauges(&datadog, interval) {
+                return;
+            }
+        });
+}
+
+/// Sends the value of every gauge registered with `interval`, returning `false` once there is none
+/// left
+fn send_gauges<C: DogstatsdClient>(datadog: &Datadog<C>, interval: Duration) -> bool {
+    let gauges = match datadog.gauges.poll(interval) {
+        Some(gauges) => gauges,
+        None => return false,
+    };
+    for gauge in gauges {
+        // A panicking callback must not prevent the other gauges from being reported
+        match panic::catch_unwind(AssertUnwindSafe(|| (gauge.callback)())) {
+            Ok(value) => datadog.do_gauge(&gauge.metric, &*value, &gauge.tags),
+            Err(_) => {
+                datadog.gauges.panics.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mocks::MockClient;
+    use crate::{TagTrackerConfiguration, EMPTY_TAGS};
+
+    /// The name, the value and the tags of the gauges sent
+    type Gauges = Arc<Mutex<Vec<(String, String, Vec<String>)>>>;
+
+    fn recording_client() -> (MockClient, Gauges) {
+        let gauges = Arc::new(Mutex::new(Vec::new()));
+        let recorded = gauges.clone();
+        let mut client = MockClient::new();
+        client.expect_gauge().returning(move |metric, value, tags| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((metric.to_string(), value.to_string(), tags));
+        });
+        (client, gauges)
+    }
+
+    #[test]
+    fn test_zero_interval_is_raised_to_the_minimum() {
+        let (client, _) = recording_client();
+        let dd = Datadog::new(client, TagTrackerConfiguration::new()).with_gauge_interval(Duration::ZERO);
+        assert_eq!(dd.gauge_interval, MIN_GAUGE_INTERVAL);
+    }
+
+    /// Long enough for the polling threads to never send anything while a test runs, the tests
+    /// send the gauges themselves with [send_gauges]
+    const HOURLY: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn test_each_interval_is_polled_on_its_own() {
+        let (client, gauges) = recording_client();
+        let dd = Datadog::new(client, TagTrackerConfiguration::new()).with_gauge_interval(HOURLY);
+        let twice_a_day = Duration::from_secs(12 * 3600);
+
+        let _hourly = dd.do_register_gauge("hourly", EMPTY_TAGS, || 1);
+        let _twice_a_day =
+            dd.clone()
+                .with_gauge_interval(twice_a_day)
+                .do_register_gauge("twice_a_day", EMPTY_TAGS, || 2);
+        assert_eq!(dd.gauges.lock().polling, vec![HOURLY, twice_a_day]);
+
+        assert!(send_gauges(&dd, twice_a_day));
+        assert_eq!(
+            *gauges.lock().unwrap(),
+            vec![("twice_a_day".to_string(), "2".to_string(), vec![])]
+        );
+    }
+
+    #[test]
+    fn test_registered_gauges_are_polled_until_deregistered() {
+        let (client, gauges) = recording_client();
+        let dd = Datadog::new(client, TagTrackerConfiguration::new())
+            .with_namespace("billing")
+            .with_gauge_interval(HOURLY);
+        let depth = Arc::new(AtomicU64::new(3));
+        let polled = depth.clone();
+
+        let handle = dd.do_register_gauge("queue.depth", ["queue:emails"], move || polled.load(Ordering::Relaxed));
+        let panicking = dd.do_register_gauge("broken", EMPTY_TAGS, || -> f64 { panic!("broken gauge") });
+        assert!(send_gauges(&dd, HOURLY));
+        depth.store(5, Ordering::Relaxed);
+        assert!(send_gauges(&dd, HOURLY));
+
+        let depths: Vec<_> = gauges
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(metric, value, tags)| {
+                assert_eq!(metric, "billing.queue.depth");
+                assert!(tags.contains(&"queue:emails".to_string()));
+                value.clone()
+            })
+            .collect();
+        assert_eq!(depths, vec!["3", "5"]);
+        assert_eq!(dd.do_stats().gauge_callback_panics, 2);
+
+        handle.deregister();
+        assert!(send_gauges(&dd, HOURLY));
+        assert_eq!(gauges.lock().unwrap().len(), 2);
+
+        drop(panicking);
+        assert!(!send_gauges(&dd, HOURLY));
+        assert!(dd.gauges.lock().polling.is_empty());
+    }
+}
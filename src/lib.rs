@@ -45,6 +45,10 @@
 //! time!("test", || { println!("expensive computation");}; "some" => "data");
 //! # timing!("test", 20);
 //! timing!("test", 20; "some" => "data");
+//! # incr!("test", @0.5);
+//! // Only sends 10% of the calls, see `Datadog::incr_with_rate`
+//! incr!("test", @0.1; "some" => "data");
+//! timing!("test", 20, @0.5; "some" => "data");
 //! # gauge!("test", "gauge value");
 //! gauge!("test", "gauge value"; "some" => "data");
 //! # histogram!("test", "histogram value");
@@ -97,6 +101,24 @@
 //! incr!(Metric::Ringo; "play" => "drums");
 //! ```
 //!
+//! ## Batching and shutdown
+//!
+//! With [Configuration::with_batching_options](configuration::Configuration::with_batching_options)
+//! the metrics are buffered and sent by a background thread. The pending metrics can be sent with
+//! [Datadog::flush], and [Datadog::shutdown] should be called before the process exits, e.g. by
+//! holding the guard returned by [Datadog::shutdown_guard] in `main`.
+//!
+//! ## Contextual tags
+//!
+//! Tags which apply to every metric emitted while handling a request or a job can be set once with
+//! [context::with_tags], or attached to a future with [context::ContextFutureExt::with_tags].
+//!
+//! ## Testing
+//!
+//! With the `testing` feature enabled, the `testing` module provides a client which records every
+//! emitted metric in memory, and can be installed as the global instance to assert on the metrics
+//! emitted by the application code.
+//!
 //! ## Note - Avoid high tag cardinality!
 //! It's important to avoid passing a large number of values for a given tag, as Datadog tracks each
 //! unique combination of tag values as a separate metric, which can significantly impact billing.
@@ -113,23 +135,48 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![doc(issue_tracker_base_url = "https://github.com/primait/prima_datadog.rs/issues")]
 
-use std::future::Future;
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use configuration::Configuration;
+use configuration::{Configuration, TimingOptions, DEFAULT_GAUGE_INTERVAL};
 pub use dogstatsd::{EventAlertType, EventOptions, EventPriority, ServiceCheckOptions, ServiceStatus};
 use once_cell::sync::OnceCell;
 
-pub use client::DogstatsdClient;
+use aggregation::AggregatingClient;
+use client::{duration_millis, GlobalClient};
+pub use client::{ClientStats, DogstatsdClient, DynDogstatsdClient};
+use gauge_registry::{GaugeHandle, GaugeRegistry, MIN_GAUGE_INTERVAL};
+use statsd::StatsdClient;
 pub use tracker::*;
+pub use value::MetricValue;
+use value::ValueBuffer;
 
 use crate::error::Error;
 
+pub mod aggregation;
 mod client;
 pub mod configuration;
+pub mod context;
 pub mod error;
+pub mod gauge_registry;
 mod macros;
+pub mod scope;
+pub mod shutdown_guard;
+pub mod statsd;
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod timing_guard;
 pub mod tracker;
+pub mod value;
 
 #[cfg(test)]
 #[path = "tests/mod.rs"]
@@ -162,31 +209,82 @@ where
 /// the type inside the empty slice.
 pub const EMPTY_TAGS: &[&str] = &[];
 
-static INSTANCE: OnceCell<Datadog<dogstatsd::Client>> = OnceCell::new();
+static INSTANCE: OnceCell<Datadog<GlobalClient>> = OnceCell::new();
 
 /// The Datadog type is the main entry point for the library
+///
+/// The static functions (and the macros) use the global instance set up by [Datadog::init], while
+/// the `do_*` methods can be used on instances created with [Datadog::new].
 pub struct Datadog<C: DogstatsdClient> {
     /// an instance of a dogstatsd::Client
-    inner: C,
+    inner: Arc<C>,
     /// Tracking for high tag cardinality
-    tag_tracker: Tracker,
+    tag_tracker: Arc<Tracker>,
+    /// The values which could not be sent, e.g. NaN
+    invalid_values: Arc<AtomicU64>,
+    /// How the measured durations are sent
+    timing_options: TimingOptions,
+    /// Prepended to every metric name, joined with a '.'
+    namespace: Option<Arc<str>>,
+    /// Added to every metric, event and service check
+    default_tags: Arc<[String]>,
+    /// The gauges polled by [Datadog::do_register_gauge]
+    gauges: Arc<GaugeRegistry>,
+    /// How often the registered gauges are polled
+    gauge_interval: Duration,
 }
 
 impl Datadog<dogstatsd::Client> {
     /// Initializes a Datadog instance with a struct that implements the [Configuration] trait.
     /// Make sure that you run it only once otherwise you will get an error.
+    ///
+    /// With [Configuration::with_validation], fails if the configuration is not valid, see
+    /// [Configuration::validate].
     pub fn init(mut configuration: Configuration) -> Result<(), Error> {
+        if configuration.validation() {
+            configuration.validate()?;
+        }
+        Self::init_global(move || {
+            let client = StatsdClient::new(&configuration)?;
+            let tracker_config = configuration.take_tracker_config();
+            let client: GlobalClient = match configuration.aggregation_options() {
+                Some(options) => Box::new(AggregatingClient::new(client, options)?),
+                None => Box::new(client),
+            };
+            Ok(Datadog::new(client, tracker_config)
+                .with_timing_options(configuration.timing_options())
+                .with_gauge_interval(configuration.gauge_interval()))
+        })
+    }
+
+    /// Initializes the global Datadog instance with a custom [DynDogstatsdClient] implementation.
+    /// The static functions and the macros will dispatch to this client.
+    ///
+    /// Namespace and default tags are not applied by this crate, they are up to the client.
+    /// Make sure that you run it only once otherwise you will get an error.
+    ///
+    /// ```
+    /// use prima_datadog::{configuration::Configuration, Datadog, TagTrackerConfiguration};
+    ///
+    /// // Any type implementing `DynDogstatsdClient` works, e.g. a wrapper around another client
+    /// let client = dogstatsd::Client::new(Configuration::new("0.0.0.0:1234", "namespace").into()).unwrap();
+    /// Datadog::init_with_client(client, TagTrackerConfiguration::new()).unwrap();
+    /// ```
+    pub fn init_with_client<C>(client: C, tracker_config: TagTrackerConfiguration) -> Result<(), Error>
+    where
+        C: DynDogstatsdClient + 'static,
+    {
+        Self::init_global(move || Ok(Datadog::new(Box::new(client) as GlobalClient, tracker_config)))
+    }
+
+    /// Sets the global instance, building it with `init` only if it was never initialized before.
+    pub(crate) fn init_global(init: impl FnOnce() -> Result<Datadog<GlobalClient>, Error>) -> Result<(), Error> {
         let mut initialized: bool = false;
 
         // the closure is guaranteed to execute only once
         let _ = INSTANCE.get_or_try_init::<_, Error>(|| {
             initialized = true;
-
-            let tracker_config = configuration.take_tracker_config();
-            let dogstatsd_client_options: dogstatsd::Options = configuration.into();
-
-            let client: dogstatsd::Client = dogstatsd::Client::new(dogstatsd_client_options)?;
-            Ok(Self::new(client, tracker_config))
+            init()
         })?;
 
         if initialized {
@@ -217,7 +315,8 @@ impl Datadog<dogstatsd::Client> {
         }
     }
 
-    /// Time a block of code (reports in ms)
+    /// Time a block of code (reports in ms, unless configured otherwise with
+    /// [Configuration::with_timing_options])
     pub fn time<S, F, O>(metric: impl AsRef<str>, tags: impl TagsProvider<S>, block: F) -> O
     where
         S: AsRef<str>,
@@ -235,6 +334,7 @@ impl Datadog<dogstatsd::Client> {
         S: AsRef<str> + Sync,
         F: FnOnce() -> T + Send,
         T: Future<Output = O> + Send,
+        O: Send,
     {
         if let Some(instance) = INSTANCE.get() {
             instance.do_async_time(metric.as_ref(), tags, block).await
@@ -250,31 +350,191 @@ impl Datadog<dogstatsd::Client> {
         }
     }
 
+    /// Send a timing metric measured as a [Duration]. It is sent in whole milliseconds, unless
+    /// configured otherwise with [Configuration::with_timing_options].
+    pub fn timing_duration<S: AsRef<str>>(metric: impl AsRef<str>, duration: Duration, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_timing_duration(metric.as_ref(), duration, tags);
+        }
+    }
+
     /// Report an arbitrary value as a gauge
-    pub fn gauge<S: AsRef<str>>(metric: impl AsRef<str>, value: impl AsRef<str>, tags: impl TagsProvider<S>) {
+    pub fn gauge<S: AsRef<str>>(metric: impl AsRef<str>, value: impl MetricValue, tags: impl TagsProvider<S>) {
         if let Some(instance) = INSTANCE.get() {
-            instance.do_gauge(metric.as_ref(), value.as_ref(), tags);
+            instance.do_gauge(metric.as_ref(), value, tags);
         }
     }
 
     /// Report a value in a histogram
-    pub fn histogram<S: AsRef<str>>(metric: impl AsRef<str>, value: impl AsRef<str>, tags: impl TagsProvider<S>) {
+    pub fn histogram<S: AsRef<str>>(metric: impl AsRef<str>, value: impl MetricValue, tags: impl TagsProvider<S>) {
         if let Some(instance) = INSTANCE.get() {
-            instance.do_histogram(metric.as_ref(), value.as_ref(), tags);
+            instance.do_histogram(metric.as_ref(), value, tags);
         }
     }
 
     /// Report a value in a distribution
-    pub fn distribution<S: AsRef<str>>(metric: impl AsRef<str>, value: impl AsRef<str>, tags: impl TagsProvider<S>) {
+    pub fn distribution<S: AsRef<str>>(metric: impl AsRef<str>, value: impl MetricValue, tags: impl TagsProvider<S>) {
         if let Some(instance) = INSTANCE.get() {
-            instance.do_distribution(metric.as_ref(), value.as_ref(), tags);
+            instance.do_distribution(metric.as_ref(), value, tags);
         }
     }
 
     /// Report a value in a set
-    pub fn set<S: AsRef<str>>(metric: impl AsRef<str>, value: impl AsRef<str>, tags: impl TagsProvider<S>) {
+    pub fn set<S: AsRef<str>>(metric: impl AsRef<str>, value: impl MetricValue, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_set(metric.as_ref(), value, tags);
+        }
+    }
+
+    /// Make an arbitrary change to a StatsD counter, as of `timestamp`
+    ///
+    /// The points submitted with an explicit timestamp (DogStatsD protocol v1.3, agent 7.40 or
+    /// later) are not aggregated by the agent, use it to submit historical data e.g. when replaying
+    /// a batch job. Timestamps in the future are rejected: the metric is not sent and counted in
+    /// [ClientStats::invalid_values].
+    ///
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    /// use prima_datadog::{Datadog, EMPTY_TAGS};
+    ///
+    /// let yesterday = SystemTime::now() - Duration::from_secs(24 * 3600);
+    /// Datadog::count_at("quotes.replayed", 12, yesterday, EMPTY_TAGS);
+    /// ```
+    pub fn count_at<S: AsRef<str>>(
+        metric: impl AsRef<str>,
+        count: i64,
+        timestamp: SystemTime,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_count_at(metric.as_ref(), count, timestamp, tags);
+        }
+    }
+
+    /// Report an arbitrary value as a gauge, as of `timestamp`, see [Datadog::count_at]
+    pub fn gauge_at<S: AsRef<str>>(
+        metric: impl AsRef<str>,
+        value: impl MetricValue,
+        timestamp: SystemTime,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_gauge_at(metric.as_ref(), value, timestamp, tags);
+        }
+    }
+
+    /// Registers a gauge whose value is returned by `callback`, polled by a background thread every
+    /// [Configuration::with_gauge_interval] until the returned handle is dropped.
+    ///
+    /// A callback which panics is skipped, without affecting the other gauges, and counted in
+    /// [ClientStats::gauge_callback_panics]. If the global instance is not initialized the callback
+    /// is never called.
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use prima_datadog::Datadog;
+    ///
+    /// let queue = Arc::new(Mutex::new(Vec::<String>::new()));
+    /// let polled = queue.clone();
+    /// let handle = Datadog::register_gauge("queue.depth", ["queue:emails"], move || polled.lock().unwrap().len());
+    /// // ...
+    /// handle.deregister();
+    /// ```
+    pub fn register_gauge<S: AsRef<str>, V: MetricValue + 'static>(
+        metric: impl AsRef<str>,
+        tags: impl TagsProvider<S>,
+        callback: impl Fn() -> V + Send + Sync + 'static,
+    ) -> GaugeHandle {
+        match INSTANCE.get() {
+            Some(instance) => instance.do_register_gauge(metric.as_ref(), tags, callback),
+            None => GaugeHandle::inert(),
+        }
+    }
+
+    /// Increment a StatsD counter, sampled at `rate`
+    ///
+    /// Only a `rate` fraction (between 0 and 1) of the calls is sent, with the rate so that the
+    /// agent scales the value back up. Use it for very hot metrics, to save the cost of sending
+    /// every call.
+    pub fn incr_with_rate<S: AsRef<str>>(metric: impl AsRef<str>, rate: f64, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_incr_with_rate(metric.as_ref(), rate, tags);
+        }
+    }
+
+    /// Decrement a StatsD counter, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn decr_with_rate<S: AsRef<str>>(metric: impl AsRef<str>, rate: f64, tags: impl TagsProvider<S>) {
         if let Some(instance) = INSTANCE.get() {
-            instance.do_set(metric.as_ref(), value.as_ref(), tags);
+            instance.do_decr_with_rate(metric.as_ref(), rate, tags);
+        }
+    }
+
+    /// Make an arbitrary change to a StatsD counter, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn count_with_rate<S: AsRef<str>>(metric: impl AsRef<str>, count: i64, rate: f64, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_count_with_rate(metric.as_ref(), count, rate, tags);
+        }
+    }
+
+    /// Send your own timing metric in milliseconds, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn timing_with_rate<S: AsRef<str>>(metric: impl AsRef<str>, ms: i64, rate: f64, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_timing_with_rate(metric.as_ref(), ms, rate, tags);
+        }
+    }
+
+    /// Report a value in a histogram, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn histogram_with_rate<S: AsRef<str>>(
+        metric: impl AsRef<str>,
+        value: impl MetricValue,
+        rate: f64,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_histogram_with_rate(metric.as_ref(), value, rate, tags);
+        }
+    }
+
+    /// Report a value in a distribution, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn distribution_with_rate<S: AsRef<str>>(
+        metric: impl AsRef<str>,
+        value: impl MetricValue,
+        rate: f64,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_distribution_with_rate(metric.as_ref(), value, rate, tags);
+        }
+    }
+
+    /// Report several values in a histogram at once
+    ///
+    /// The [StatsdClient] packs them in as few lines as possible, as
+    /// supported by the DogStatsD protocol v1.1. The values which can't be sent are skipped.
+    pub fn histogram_many<S: AsRef<str>>(
+        metric: impl AsRef<str>,
+        values: &[impl MetricValue],
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_histogram_many(metric.as_ref(), values, tags);
+        }
+    }
+
+    /// Report several values in a distribution at once, see [Datadog::histogram_many]
+    ///
+    /// ```
+    /// use prima_datadog::{Datadog, EMPTY_TAGS};
+    ///
+    /// Datadog::distribution_many("request.size", &[1.5, 2.0, 3.25], EMPTY_TAGS);
+    /// ```
+    pub fn distribution_many<S: AsRef<str>>(
+        metric: impl AsRef<str>,
+        values: &[impl MetricValue],
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_distribution_many(metric.as_ref(), values, tags);
         }
     }
 
@@ -318,170 +578,646 @@ impl Datadog<dogstatsd::Client> {
     ) -> timing_guard::TimingGuard<S, P> {
         timing_guard::TimingGuard::new(metric, tags)
     }
+
+    /// Sends every metric buffered by the global client, blocking until they have been handed to
+    /// the transport. It only has an effect if batching is enabled.
+    pub fn flush() -> Result<(), Error> {
+        match INSTANCE.get() {
+            Some(instance) => instance.do_flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Flushes the global client and stops it, waiting at most `timeout`.
+    ///
+    /// Metrics emitted after the shutdown are dropped, so this should be called right before the
+    /// process exits, e.g. through a [ShutdownGuard](shutdown_guard::ShutdownGuard).
+    pub fn shutdown(timeout: Duration) -> Result<(), Error> {
+        match INSTANCE.get() {
+            Some(instance) => instance.do_shutdown(timeout),
+            None => Ok(()),
+        }
+    }
+
+    /// The number of metrics dropped by the global client, e.g. because the queue of the background
+    /// worker was full. See [WorkerOptions](configuration::WorkerOptions).
+    pub fn dropped_items() -> u64 {
+        Self::stats().dropped_items
+    }
+
+    /// A snapshot of the internal counters of the global client and tag tracker
+    pub fn stats() -> ClientStats {
+        INSTANCE.get().map(|instance| instance.do_stats()).unwrap_or_default()
+    }
+
+    /// Acquire a shutdown guard.
+    /// When this guard is dropped, it will shut down the global instance waiting at most timeout.
+    pub fn shutdown_guard(timeout: Duration) -> shutdown_guard::ShutdownGuard {
+        shutdown_guard::ShutdownGuard::new(timeout)
+    }
 }
 
 impl<C: DogstatsdClient> Datadog<C> {
-    fn new(client: C, tracker_config: TagTrackerConfiguration) -> Self {
+    /// Creates a new instance, independent from the global one, with the given client and tag
+    /// tracker configuration.
+    ///
+    /// This is useful for libraries, which can accept a [Datadog] instance instead of relying on the
+    /// global instance being initialized. Instances are cheap to clone, and clones share the same
+    /// client and tag tracker.
+    ///
+    /// ```
+    /// use prima_datadog::{configuration::Configuration, Datadog, DogstatsdClient, TagTrackerConfiguration, EMPTY_TAGS};
+    ///
+    /// struct Billing<C: DogstatsdClient> {
+    ///     datadog: Datadog<C>,
+    /// }
+    ///
+    /// impl<C: DogstatsdClient> Billing<C> {
+    ///     fn pay(&self) {
+    ///         self.datadog.do_incr("payments", EMPTY_TAGS);
+    ///     }
+    /// }
+    ///
+    /// let client = dogstatsd::Client::new(Configuration::new("0.0.0.0:1234", "").into()).unwrap();
+    /// let datadog = Datadog::new(client, TagTrackerConfiguration::new())
+    ///     .with_namespace("billing")
+    ///     .with_tag("component", &"payments");
+    /// Billing { datadog: datadog.clone() }.pay();
+    /// ```
+    pub fn new(client: C, tracker_config: TagTrackerConfiguration) -> Self {
         Self {
-            inner: client,
-            tag_tracker: tracker_config.build(),
+            inner: Arc::new(client),
+            tag_tracker: Arc::new(tracker_config.build()),
+            invalid_values: Arc::new(AtomicU64::new(0)),
+            timing_options: TimingOptions::default(),
+            namespace: None,
+            default_tags: Arc::new([]),
+            gauges: Arc::new(GaugeRegistry::default()),
+            gauge_interval: DEFAULT_GAUGE_INTERVAL,
+        }
+    }
+
+    /// Sets the namespace of this instance, which is prepended to every metric name joined with a '.'.
+    /// The namespace is not applied to events and service checks.
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(Arc::from(namespace));
+        self
+    }
+
+    /// Sets the unit of the durations measured by this instance, e.g. by [Datadog::do_time], and
+    /// whether they are sent as distributions. See [Configuration::with_timing_options].
+    pub fn with_timing_options(mut self, timing_options: TimingOptions) -> Self {
+        self.timing_options = timing_options;
+        self
+    }
+
+    /// Sets how often the gauges registered with [Datadog::do_register_gauge] are polled, 10 seconds
+    /// by default. It only applies to the gauges registered afterwards, each interval being polled
+    /// by its own thread. Intervals shorter than 10 milliseconds are raised to 10 milliseconds.
+    pub fn with_gauge_interval(mut self, interval: Duration) -> Self {
+        self.gauge_interval = interval.max(MIN_GAUGE_INTERVAL);
+        self
+    }
+
+    /// Adds a tag to every metric, event and service check emitted by this instance.
+    pub fn with_tag<T: Display + ?Sized>(mut self, key: &str, value: &T) -> Self {
+        let mut default_tags = self.default_tags.to_vec();
+        default_tags.push(format!("{key}:{value}"));
+        self.default_tags = default_tags.into();
+        self
+    }
+
+    /// Returns the namespace of this instance, if any
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Returns the tags added to everything emitted by this instance
+    pub fn default_tags(&self) -> &[String] {
+        &self.default_tags
+    }
+
+    fn metric_name<'a>(&self, metric: &'a str) -> Cow<'a, str> {
+        match self.namespace.as_deref() {
+            Some(namespace) => Cow::Owned(format!("{namespace}.{metric}")),
+            None => Cow::Borrowed(metric),
+        }
+    }
+
+    fn merge_tags<'a, S: AsRef<str> + 'a>(
+        &'a self,
+        tags: &'a impl TagsProvider<S>,
+        context_tags: &'a [String],
+    ) -> Vec<&'a str> {
+        tags.as_ref()
+            .iter()
+            .map(|tag| tag.as_ref())
+            .chain(context_tags.iter().map(String::as_str))
+            .chain(self.default_tags.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// Formats the value, counting the values which can't be sent
+    fn format_value<'a>(&self, value: &'a impl MetricValue, buffer: &'a mut ValueBuffer) -> Option<&'a str> {
+        let value = value.format_value(buffer);
+        if value.is_none() {
+            self.invalid_values.fetch_add(1, Ordering::Relaxed);
+        }
+        value
+    }
+
+    /// Converts the timestamp to seconds since the unix epoch, counting the ones in the future or
+    /// before the epoch as invalid values
+    fn unix_timestamp(&self, timestamp: SystemTime) -> Option<u64> {
+        match timestamp.duration_since(UNIX_EPOCH) {
+            Ok(since_epoch) if timestamp <= SystemTime::now() => Some(since_epoch.as_secs()),
+            _ => {
+                self.invalid_values.fetch_add(1, Ordering::Relaxed);
+                None
+            }
         }
     }
 
-    pub(crate) fn do_incr<S: AsRef<str>>(&self, metric: impl AsRef<str>, tags: impl TagsProvider<S>) {
-        self.inner.incr(
-            metric.as_ref(),
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-        );
+    /// Formats the values, skipping and counting the ones which can't be sent
+    fn format_values(&self, values: &[impl MetricValue]) -> Vec<String> {
+        let mut buffer = ValueBuffer::new();
+        values
+            .iter()
+            .filter_map(|value| self.format_value(value, &mut buffer).map(str::to_string))
+            .collect()
+    }
+
+    /// Increment a StatsD counter
+    pub fn do_incr<S: AsRef<str>>(&self, metric: impl AsRef<str>, tags: impl TagsProvider<S>) {
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::incr(&*self.inner, &metric, &tags);
     }
 
-    pub(crate) fn do_decr<S: AsRef<str>>(&self, metric: impl AsRef<str>, tags: impl TagsProvider<S>) {
-        self.inner.decr(
-            metric.as_ref(),
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-        );
+    /// Decrement a StatsD counter
+    pub fn do_decr<S: AsRef<str>>(&self, metric: impl AsRef<str>, tags: impl TagsProvider<S>) {
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::decr(&*self.inner, &metric, &tags);
     }
 
-    pub(crate) fn do_count<S: AsRef<str>>(&self, metric: impl AsRef<str>, count: i64, tags: impl TagsProvider<S>) {
-        self.inner.count(
-            metric.as_ref(),
-            count,
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-        );
+    /// Make an arbitrary change to a StatsD counter
+    pub fn do_count<S: AsRef<str>>(&self, metric: impl AsRef<str>, count: i64, tags: impl TagsProvider<S>) {
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::count(&*self.inner, &metric, count, &tags);
     }
 
-    pub(crate) fn do_time<S, F, O>(&self, metric: impl AsRef<str>, tags: impl TagsProvider<S>, block: F) -> O
+    /// Time a block of code (reports in ms)
+    pub fn do_time<S, F, O>(&self, metric: impl AsRef<str>, tags: impl TagsProvider<S>, block: F) -> O
     where
         S: AsRef<str>,
         F: FnOnce() -> O,
     {
-        self.inner.time(
-            metric.as_ref(),
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-            block,
-        )
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        if self.timing_options.is_default() {
+            return DogstatsdClient::time(&*self.inner, &metric, tags, block);
+        }
+        let start = Instant::now();
+        let output = block();
+        self.send_duration(&metric, start.elapsed(), &tags);
+        output
     }
 
-    pub(crate) async fn do_async_time<S, F, T, O>(
-        &self,
-        metric: &str,
-        tags: impl TagsProvider<S> + Send + Sync,
-        block: F,
-    ) -> O
+    /// Time a future (reports in ms)
+    pub async fn do_async_time<S, F, T, O>(&self, metric: &str, tags: impl TagsProvider<S> + Send + Sync, block: F) -> O
     where
         S: AsRef<str> + Sync,
         F: FnOnce() -> T + Send,
         T: Future<Output = O> + Send,
+        O: Send,
     {
-        self.inner
-            .async_time(
-                metric.as_ref(),
-                self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-                block,
-            )
-            .await
+        let metric = self.metric_name(metric);
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        if self.timing_options.is_default() {
+            return DogstatsdClient::async_time(&*self.inner, &metric, tags, block).await;
+        }
+        let start = Instant::now();
+        let output = block().await;
+        self.send_duration(&metric, start.elapsed(), &tags);
+        output
     }
 
-    pub(crate) fn do_timing<S: AsRef<str>>(&self, metric: impl AsRef<str>, ms: i64, tags: impl TagsProvider<S>) {
-        self.inner.timing(
-            metric.as_ref(),
-            ms,
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-        );
+    /// Send your own timing metric in milliseconds
+    pub fn do_timing<S: AsRef<str>>(&self, metric: impl AsRef<str>, ms: i64, tags: impl TagsProvider<S>) {
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::timing(&*self.inner, &metric, ms, &tags);
     }
 
-    pub(crate) fn do_gauge<S: AsRef<str>>(
+    /// Send a timing metric measured as a [Duration], in the unit of the [TimingOptions]
+    pub fn do_timing_duration<S: AsRef<str>>(
         &self,
         metric: impl AsRef<str>,
-        value: impl AsRef<str>,
+        duration: Duration,
         tags: impl TagsProvider<S>,
     ) {
-        self.inner.gauge(
-            metric.as_ref(),
-            value.as_ref(),
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-        );
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        self.send_duration(&metric, duration, &tags);
     }
 
-    pub(crate) fn do_histogram<S: AsRef<str>>(
+    fn send_duration(&self, metric: &str, duration: Duration, tags: &[&str]) {
+        if self.timing_options.is_default() {
+            DynDogstatsdClient::timing(&*self.inner, metric, duration_millis(duration), tags);
+            return;
+        }
+        let mut buffer = ValueBuffer::new();
+        let value = match self.timing_options.unit.format(duration, &mut buffer) {
+            Some(value) => value,
+            None => return,
+        };
+        if self.timing_options.as_distribution {
+            DynDogstatsdClient::distribution(&*self.inner, metric, value, tags);
+        } else if let Some(suffix) = self.timing_options.unit.timer_suffix() {
+            DynDogstatsdClient::timing_value(&*self.inner, &format!("{metric}.{suffix}"), value, tags);
+        } else {
+            DynDogstatsdClient::timing_value(&*self.inner, metric, value, tags);
+        }
+    }
+
+    /// Report an arbitrary value as a gauge
+    pub fn do_gauge<S: AsRef<str>>(
         &self,
         metric: impl AsRef<str>,
-        value: impl AsRef<str>,
+        value: impl MetricValue,
         tags: impl TagsProvider<S>,
     ) {
-        self.inner.histogram(
-            metric.as_ref(),
-            value.as_ref(),
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-        );
+        let mut buffer = ValueBuffer::new();
+        let value = match self.format_value(&value, &mut buffer) {
+            Some(value) => value,
+            None => return,
+        };
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::gauge(&*self.inner, &metric, value, &tags);
     }
 
-    pub(crate) fn do_distribution<S: AsRef<str>>(
+    /// Make an arbitrary change to a StatsD counter, as of `timestamp`, see [Datadog::count_at]
+    pub fn do_count_at<S: AsRef<str>>(
         &self,
         metric: impl AsRef<str>,
-        value: impl AsRef<str>,
+        count: i64,
+        timestamp: SystemTime,
         tags: impl TagsProvider<S>,
     ) {
-        self.inner.distribution(
-            metric.as_ref(),
-            value.as_ref(),
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-        );
+        let timestamp = match self.unix_timestamp(timestamp) {
+            Some(timestamp) => timestamp,
+            None => return,
+        };
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::count_at(&*self.inner, &metric, count, timestamp, &tags);
     }
 
-    pub(crate) fn do_set<S: AsRef<str>>(
+    /// Report an arbitrary value as a gauge, as of `timestamp`, see [Datadog::count_at]
+    pub fn do_gauge_at<S: AsRef<str>>(
         &self,
         metric: impl AsRef<str>,
-        value: impl AsRef<str>,
+        value: impl MetricValue,
+        timestamp: SystemTime,
         tags: impl TagsProvider<S>,
     ) {
-        self.inner.set(
-            metric.as_ref(),
-            value.as_ref(),
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-        );
+        let timestamp = match self.unix_timestamp(timestamp) {
+            Some(timestamp) => timestamp,
+            None => return,
+        };
+        let mut buffer = ValueBuffer::new();
+        let value = match self.format_value(&value, &mut buffer) {
+            Some(value) => value,
+            None => return,
+        };
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::gauge_at(&*self.inner, &metric, value, timestamp, &tags);
     }
 
-    pub(crate) fn do_service_check<S: AsRef<str>>(
+    /// Registers a gauge polled every gauge interval, see [Datadog::register_gauge]. The context
+    /// tags are the ones of the registration.
+    pub fn do_register_gauge<S: AsRef<str>, V: MetricValue + 'static>(
         &self,
         metric: impl AsRef<str>,
-        value: ServiceStatus,
         tags: impl TagsProvider<S>,
-        options: Option<ServiceCheckOptions>,
+        callback: impl Fn() -> V + Send + Sync + 'static,
+    ) -> GaugeHandle
+    where
+        C: 'static,
+    {
+        let context_tags = context::current_tags();
+        let tags = tags
+            .as_ref()
+            .iter()
+            .map(|tag| tag.as_ref().to_string())
+            .chain(context_tags.iter().cloned())
+            .collect();
+        let callback = Box::new(move || Box::new(callback()) as Box<dyn MetricValue>);
+        self.register_gauge_callback(metric.as_ref().to_string(), tags, callback)
+    }
+
+    /// Report a value in a histogram
+    pub fn do_histogram<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        value: impl MetricValue,
+        tags: impl TagsProvider<S>,
     ) {
-        self.inner.service_check(
-            metric.as_ref(),
-            value,
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-            options,
-        );
+        let mut buffer = ValueBuffer::new();
+        let value = match self.format_value(&value, &mut buffer) {
+            Some(value) => value,
+            None => return,
+        };
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::histogram(&*self.inner, &metric, value, &tags);
     }
 
-    pub(crate) fn do_event<S: AsRef<str>>(
+    /// Report a value in a distribution
+    pub fn do_distribution<S: AsRef<str>>(
         &self,
         metric: impl AsRef<str>,
-        text: impl AsRef<str>,
+        value: impl MetricValue,
+        tags: impl TagsProvider<S>,
+    ) {
+        let mut buffer = ValueBuffer::new();
+        let value = match self.format_value(&value, &mut buffer) {
+            Some(value) => value,
+            None => return,
+        };
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::distribution(&*self.inner, &metric, value, &tags);
+    }
+
+    /// Report a value in a set
+    pub fn do_set<S: AsRef<str>>(&self, metric: impl AsRef<str>, value: impl MetricValue, tags: impl TagsProvider<S>) {
+        let mut buffer = ValueBuffer::new();
+        let value = match self.format_value(&value, &mut buffer) {
+            Some(value) => value,
+            None => return,
+        };
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::set(&*self.inner, &metric, value, &tags);
+    }
+
+    /// Increment a StatsD counter, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn do_incr_with_rate<S: AsRef<str>>(&self, metric: impl AsRef<str>, rate: f64, tags: impl TagsProvider<S>) {
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::incr_with_rate(&*self.inner, &metric, rate, &tags);
+    }
+
+    /// Decrement a StatsD counter, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn do_decr_with_rate<S: AsRef<str>>(&self, metric: impl AsRef<str>, rate: f64, tags: impl TagsProvider<S>) {
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::decr_with_rate(&*self.inner, &metric, rate, &tags);
+    }
+
+    /// Make an arbitrary change to a StatsD counter, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn do_count_with_rate<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        count: i64,
+        rate: f64,
         tags: impl TagsProvider<S>,
     ) {
-        self.inner.event(
-            metric.as_ref(),
-            text.as_ref(),
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-        );
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::count_with_rate(&*self.inner, &metric, count, rate, &tags);
     }
 
-    pub(crate) fn do_event_with_options<S: AsRef<str>>(
+    /// Send your own timing metric in milliseconds, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn do_timing_with_rate<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        ms: i64,
+        rate: f64,
+        tags: impl TagsProvider<S>,
+    ) {
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::timing_with_rate(&*self.inner, &metric, ms, rate, &tags);
+    }
+
+    /// Report a value in a histogram, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn do_histogram_with_rate<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        value: impl MetricValue,
+        rate: f64,
+        tags: impl TagsProvider<S>,
+    ) {
+        let mut buffer = ValueBuffer::new();
+        let value = match self.format_value(&value, &mut buffer) {
+            Some(value) => value,
+            None => return,
+        };
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::histogram_with_rate(&*self.inner, &metric, value, rate, &tags);
+    }
+
+    /// Report a value in a distribution, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn do_distribution_with_rate<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        value: impl MetricValue,
+        rate: f64,
+        tags: impl TagsProvider<S>,
+    ) {
+        let mut buffer = ValueBuffer::new();
+        let value = match self.format_value(&value, &mut buffer) {
+            Some(value) => value,
+            None => return,
+        };
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::distribution_with_rate(&*self.inner, &metric, value, rate, &tags);
+    }
+
+    /// Report several values in a histogram at once, see [Datadog::histogram_many]
+    pub fn do_histogram_many<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        values: &[impl MetricValue],
+        tags: impl TagsProvider<S>,
+    ) {
+        let values = self.format_values(values);
+        if values.is_empty() {
+            return;
+        }
+        let values: Vec<&str> = values.iter().map(String::as_str).collect();
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::histogram_many(&*self.inner, &metric, &values, &tags);
+    }
+
+    /// Report several values in a distribution at once, see [Datadog::histogram_many]
+    pub fn do_distribution_many<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        values: &[impl MetricValue],
+        tags: impl TagsProvider<S>,
+    ) {
+        let values = self.format_values(values);
+        if values.is_empty() {
+            return;
+        }
+        let values: Vec<&str> = values.iter().map(String::as_str).collect();
+        let metric = self.metric_name(metric.as_ref());
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, &metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::distribution_many(&*self.inner, &metric, &values, &tags);
+    }
+
+    /// Report the status of a service
+    pub fn do_service_check<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        value: ServiceStatus,
+        tags: impl TagsProvider<S>,
+        options: Option<ServiceCheckOptions>,
+    ) {
+        let metric = metric.as_ref();
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::service_check(&*self.inner, metric, value, &tags, options);
+    }
+
+    /// Send a custom event as a title and a body
+    pub fn do_event<S: AsRef<str>>(&self, metric: impl AsRef<str>, text: impl AsRef<str>, tags: impl TagsProvider<S>) {
+        let metric = metric.as_ref();
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::event(&*self.inner, metric, text.as_ref(), &tags);
+    }
+
+    /// Send a custom event as a title, a body and some options
+    pub fn do_event_with_options<S: AsRef<str>>(
         &self,
         metric: impl AsRef<str>,
         text: impl AsRef<str>,
         tags: impl TagsProvider<S>,
         options: Option<EventOptions>,
     ) {
-        self.inner.event_with_options(
-            metric.as_ref(),
-            text.as_ref(),
-            self.tag_tracker.track(&self.inner, metric.as_ref(), tags),
-            options,
-        );
+        let metric = metric.as_ref();
+        let context_tags = context::current_tags();
+        let tags = self
+            .tag_tracker
+            .track(&*self.inner, metric, self.merge_tags(&tags, &context_tags));
+        DynDogstatsdClient::event_with_options(&*self.inner, metric, text.as_ref(), &tags, options);
+    }
+
+    /// Sends every metric buffered by the client, see [DynDogstatsdClient::flush]
+    pub fn do_flush(&self) -> Result<(), Error> {
+        DynDogstatsdClient::flush(&*self.inner)
+    }
+
+    /// Flushes and stops the client, see [DynDogstatsdClient::shutdown]
+    pub fn do_shutdown(&self, timeout: Duration) -> Result<(), Error> {
+        DynDogstatsdClient::shutdown(&*self.inner, timeout)
+    }
+
+    /// The number of metrics dropped by the client, see [ClientStats::dropped_items]
+    pub fn do_dropped_items(&self) -> u64 {
+        self.do_stats().dropped_items
+    }
+
+    /// A snapshot of the internal counters of the client and tag tracker
+    pub fn do_stats(&self) -> ClientStats {
+        ClientStats {
+            tracker_events: self.tag_tracker.actions_fired(),
+            invalid_values: self.invalid_values.load(Ordering::Relaxed),
+            gauge_callback_panics: self.gauges.panics(),
+            ..DynDogstatsdClient::stats(&*self.inner)
+        }
+    }
+}
+
+impl<C: DogstatsdClient> Clone for Datadog<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            tag_tracker: self.tag_tracker.clone(),
+            invalid_values: self.invalid_values.clone(),
+            timing_options: self.timing_options,
+            namespace: self.namespace.clone(),
+            default_tags: self.default_tags.clone(),
+            gauges: self.gauges.clone(),
+            gauge_interval: self.gauge_interval,
+        }
     }
 }
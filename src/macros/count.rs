@@ -1,4 +1,6 @@
 /// Make an arbitrary change to a StatsD counter
+/// An optional sample rate can be given as `@rate`, see [Datadog::incr_with_rate](crate::Datadog::incr_with_rate)
+/// An explicit timestamp can be given as `at timestamp`, see [Datadog::count_at](crate::Datadog::count_at)
 /// NOTE: Try to minimise variation in tag values (avoid things like timestamps or ids). See note in lib docs!
 #[macro_export]
 macro_rules! count {
@@ -20,4 +22,40 @@ macro_rules! count {
     ($stat:path, $count:expr; $( $key:expr => $value:expr ), *) => {
         $crate::Datadog::count($stat.as_ref(), $count, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
     };
+    ($stat:expr, $count:expr, @$rate:expr) => {
+        $crate::Datadog::count_with_rate($stat, $count, $rate, $crate::EMPTY_TAGS);
+    };
+    ($stat:path, $count:expr, @$rate:expr) => {
+        $crate::Datadog::count_with_rate($stat.as_ref(), $count, $rate, $crate::EMPTY_TAGS);
+    };
+    ($stat:expr, $count:expr, @$rate:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::count_with_rate($stat, $count, $rate, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:path, $count:expr, @$rate:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::count_with_rate($stat.as_ref(), $count, $rate, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:expr, $count:expr, @$rate:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::count_with_rate($stat, $count, $rate, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
+    ($stat:path, $count:expr, @$rate:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::count_with_rate($stat.as_ref(), $count, $rate, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
+    ($stat:expr, $count:expr, at $ts:expr) => {
+        $crate::Datadog::count_at($stat, $count, $ts, $crate::EMPTY_TAGS);
+    };
+    ($stat:path, $count:expr, at $ts:expr) => {
+        $crate::Datadog::count_at($stat.as_ref(), $count, $ts, $crate::EMPTY_TAGS);
+    };
+    ($stat:expr, $count:expr, at $ts:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::count_at($stat, $count, $ts, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:path, $count:expr, at $ts:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::count_at($stat.as_ref(), $count, $ts, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:expr, $count:expr, at $ts:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::count_at($stat, $count, $ts, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
+    ($stat:path, $count:expr, at $ts:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::count_at($stat.as_ref(), $count, $ts, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
 }
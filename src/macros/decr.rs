@@ -1,4 +1,5 @@
 /// Decrement a StatsD counter
+/// An optional sample rate can be given as `@rate`, see [Datadog::incr_with_rate](crate::Datadog::incr_with_rate)
 /// NOTE: Try to minimise variation in tag values (avoid things like timestamps or ids). See note in lib docs!
 #[macro_export]
 macro_rules! decr {
@@ -20,4 +21,22 @@ macro_rules! decr {
     ($stat:path; $( $key:expr => $value:expr ), *) => {
         $crate::Datadog::decr($stat.as_ref(), &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
     };
+    ($stat:expr, @$rate:expr) => {
+        $crate::Datadog::decr_with_rate($stat, $rate, $crate::EMPTY_TAGS);
+    };
+    ($stat:path, @$rate:expr) => {
+        $crate::Datadog::decr_with_rate($stat.as_ref(), $rate, $crate::EMPTY_TAGS);
+    };
+    ($stat:expr, @$rate:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::decr_with_rate($stat, $rate, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:path, @$rate:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::decr_with_rate($stat.as_ref(), $rate, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:expr, @$rate:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::decr_with_rate($stat, $rate, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
+    ($stat:path, @$rate:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::decr_with_rate($stat.as_ref(), $rate, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
 }
@@ -1,4 +1,5 @@
 /// Report an arbitrary value as a gauge
+/// An explicit timestamp can be given as `at timestamp`, see [Datadog::count_at](crate::Datadog::count_at)
 /// NOTE: Try to minimise variation in tag values (avoid things like timestamps or ids). See note in lib docs!
 #[macro_export]
 macro_rules! gauge {
@@ -20,4 +21,22 @@ macro_rules! gauge {
     ($stat:path, $val:expr; $( $key:expr => $value:expr ), *) => {
         $crate::Datadog::gauge($stat.as_ref(), $val, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
     };
+    ($stat:expr, $val:expr, at $ts:expr) => {
+        $crate::Datadog::gauge_at($stat, $val, $ts, $crate::EMPTY_TAGS);
+    };
+    ($stat:path, $val:expr, at $ts:expr) => {
+        $crate::Datadog::gauge_at($stat.as_ref(), $val, $ts, $crate::EMPTY_TAGS);
+    };
+    ($stat:expr, $val:expr, at $ts:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::gauge_at($stat, $val, $ts, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:path, $val:expr, at $ts:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::gauge_at($stat.as_ref(), $val, $ts, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:expr, $val:expr, at $ts:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::gauge_at($stat, $val, $ts, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
+    ($stat:path, $val:expr, at $ts:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::gauge_at($stat.as_ref(), $val, $ts, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
 }
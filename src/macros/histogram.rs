@@ -1,4 +1,5 @@
 /// Report a value in a histogram
+/// An optional sample rate can be given as `@rate`, see [Datadog::incr_with_rate](crate::Datadog::incr_with_rate)
 /// NOTE: Try to minimise variation in tag values (avoid things like timestamps or ids). See note in lib docs!
 #[macro_export]
 macro_rules! histogram {
@@ -20,4 +21,22 @@ macro_rules! histogram {
     ($stat:path, $val:expr; $( $key:expr => $value:expr ), *) => {
         $crate::Datadog::histogram($stat.as_ref(), $val, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
     };
+    ($stat:expr, $val:expr, @$rate:expr) => {
+        $crate::Datadog::histogram_with_rate($stat, $val, $rate, $crate::EMPTY_TAGS);
+    };
+    ($stat:path, $val:expr, @$rate:expr) => {
+        $crate::Datadog::histogram_with_rate($stat.as_ref(), $val, $rate, $crate::EMPTY_TAGS);
+    };
+    ($stat:expr, $val:expr, @$rate:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::histogram_with_rate($stat, $val, $rate, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:path, $val:expr, @$rate:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::histogram_with_rate($stat.as_ref(), $val, $rate, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:expr, $val:expr, @$rate:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::histogram_with_rate($stat, $val, $rate, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
+    ($stat:path, $val:expr, @$rate:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::histogram_with_rate($stat.as_ref(), $val, $rate, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
 }
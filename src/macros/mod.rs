@@ -7,6 +7,7 @@ mod event;
 mod gauge;
 mod histogram;
 mod incr;
+mod pkg_version;
 mod service_check;
 mod set;
 mod time;
@@ -0,0 +1,9 @@
+/// The version of the calling crate, read from `CARGO_PKG_VERSION` at compile time. Meant to be
+/// used as the unified service tagging version with
+/// [Configuration::with_version](crate::configuration::Configuration::with_version)
+#[macro_export]
+macro_rules! pkg_version {
+    () => {
+        ::core::env!("CARGO_PKG_VERSION")
+    };
+}
@@ -1,4 +1,5 @@
-/// Time a block of code (reports in ms)
+/// Time a block of code (reports in ms, unless configured otherwise with
+/// [Configuration::with_timing_options](crate::configuration::Configuration::with_timing_options))
 /// NOTE: Try to minimise variation in tag values (avoid things like timestamps or ids). See note in lib docs!
 #[macro_export]
 macro_rules! time {
@@ -1,4 +1,5 @@
 /// Send your own timing metric in milliseconds
+/// An optional sample rate can be given as `@rate`, see [Datadog::incr_with_rate](crate::Datadog::incr_with_rate)
 /// NOTE: Try to minimise variation in tag values (avoid things like timestamps or ids). See note in lib docs!
 #[macro_export]
 macro_rules! timing {
@@ -20,4 +21,22 @@ macro_rules! timing {
     ($stat:path, $ms:expr; $( $key:expr => $value:expr ), *) => {
         $crate::Datadog::timing($stat.as_ref(), $ms, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
     };
+    ($stat:expr, $ms:expr, @$rate:expr) => {
+        $crate::Datadog::timing_with_rate($stat, $ms, $rate, $crate::EMPTY_TAGS);
+    };
+    ($stat:path, $ms:expr, @$rate:expr) => {
+        $crate::Datadog::timing_with_rate($stat.as_ref(), $ms, $rate, $crate::EMPTY_TAGS);
+    };
+    ($stat:expr, $ms:expr, @$rate:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::timing_with_rate($stat, $ms, $rate, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:path, $ms:expr, @$rate:expr; $( $key:literal => $value:literal ), *) => {
+        $crate::Datadog::timing_with_rate($stat.as_ref(), $ms, $rate, &[$(::core::concat!($key, ":", $value)), *]);
+    };
+    ($stat:expr, $ms:expr, @$rate:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::timing_with_rate($stat, $ms, $rate, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
+    ($stat:path, $ms:expr, @$rate:expr; $( $key:expr => $value:expr ), *) => {
+        $crate::Datadog::timing_with_rate($stat.as_ref(), $ms, $rate, &[$(::std::format!("{}:{}", $key, $value).as_str()), *]);
+    };
 }
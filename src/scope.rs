@@ -0,0 +1,370 @@
+//! Scoped handles on the global instance, see [Datadog::scoped]
+
+use std::{
+    fmt::Display,
+    future::Future,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    gauge_registry::GaugeHandle, Datadog, EventOptions, MetricValue, ServiceCheckOptions, ServiceStatus, TagsProvider,
+    INSTANCE,
+};
+
+/// A lightweight handle on the global instance, which prepends a prefix to every metric name and
+/// adds its own tags to every metric, event and service check.
+///
+/// Like the namespace, the prefix is not applied to events and service checks. Everything goes
+/// through the global instance, so the namespace, the default tags and the tag tracker of the
+/// global instance still apply. If the global instance is not initialized nothing is sent.
+///
+/// ```
+/// use prima_datadog::{Datadog, EMPTY_TAGS};
+///
+/// let billing = Datadog::scoped("billing").with_tag("component", "pricing");
+/// // Sends `billing.invoices` with the `component:pricing` tag
+/// billing.incr("invoices", EMPTY_TAGS);
+/// // Sends `billing.refunds.requested` with the `component:pricing` tag
+/// billing.scoped("refunds").incr("requested", EMPTY_TAGS);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scope {
+    prefix: String,
+    tags: Vec<String>,
+}
+
+impl Scope {
+    pub(crate) fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Adds a tag to everything emitted through this scope
+    pub fn with_tag<T: Display + ?Sized>(mut self, key: &str, value: &T) -> Self {
+        self.tags.push(format!("{key}:{value}"));
+        self
+    }
+
+    /// Creates a nested scope, whose prefix is appended to the prefix of this scope joined with a
+    /// '.', and which inherits the tags of this scope
+    pub fn scoped(&self, prefix: &str) -> Scope {
+        Scope {
+            prefix: format!("{}.{}", self.prefix, prefix),
+            tags: self.tags.clone(),
+        }
+    }
+
+    /// Returns the prefix of this scope
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns the tags added by this scope
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    fn metric_name(&self, metric: &str) -> String {
+        format!("{}.{}", self.prefix, metric)
+    }
+
+    fn merge_tags<'a, S: AsRef<str> + 'a>(&'a self, tags: &'a impl TagsProvider<S>) -> Vec<&'a str> {
+        tags.as_ref()
+            .iter()
+            .map(|tag| tag.as_ref())
+            .chain(self.tags.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// Increment a StatsD counter
+    pub fn incr<S: AsRef<str>>(&self, metric: impl AsRef<str>, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_incr(self.metric_name(metric.as_ref()), self.merge_tags(&tags));
+        }
+    }
+
+    /// Decrement a StatsD counter
+    pub fn decr<S: AsRef<str>>(&self, metric: impl AsRef<str>, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_decr(self.metric_name(metric.as_ref()), self.merge_tags(&tags));
+        }
+    }
+
+    /// Make an arbitrary change to a StatsD counter
+    pub fn count<S: AsRef<str>>(&self, metric: impl AsRef<str>, count: i64, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_count(self.metric_name(metric.as_ref()), count, self.merge_tags(&tags));
+        }
+    }
+
+    /// Time a block of code (reports in ms)
+    pub fn time<S, F, O>(&self, metric: impl AsRef<str>, tags: impl TagsProvider<S>, block: F) -> O
+    where
+        S: AsRef<str>,
+        F: FnOnce() -> O,
+    {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_time(self.metric_name(metric.as_ref()), self.merge_tags(&tags), block)
+        } else {
+            block()
+        }
+    }
+
+    /// Time a future (reports in ms)
+    pub async fn async_time<S, F, T, O>(&self, metric: &str, tags: impl TagsProvider<S> + Send + Sync, block: F) -> O
+    where
+        S: AsRef<str> + Sync,
+        F: FnOnce() -> T + Send,
+        T: Future<Output = O> + Send,
+        O: Send,
+    {
+        if let Some(instance) = INSTANCE.get() {
+            instance
+                .do_async_time(&self.metric_name(metric), self.merge_tags(&tags), block)
+                .await
+        } else {
+            block().await
+        }
+    }
+
+    /// Send your own timing metric in milliseconds
+    pub fn timing<S: AsRef<str>>(&self, metric: impl AsRef<str>, ms: i64, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_timing(self.metric_name(metric.as_ref()), ms, self.merge_tags(&tags));
+        }
+    }
+
+    /// Send a timing metric measured as a [Duration], see [Datadog::timing_duration]
+    pub fn timing_duration<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        duration: Duration,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_timing_duration(self.metric_name(metric.as_ref()), duration, self.merge_tags(&tags));
+        }
+    }
+
+    /// Report an arbitrary value as a gauge
+    pub fn gauge<S: AsRef<str>>(&self, metric: impl AsRef<str>, value: impl MetricValue, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_gauge(self.metric_name(metric.as_ref()), value, self.merge_tags(&tags));
+        }
+    }
+
+    /// Registers a gauge polled on an interval, see [Datadog::register_gauge]
+    pub fn register_gauge<S: AsRef<str>, V: MetricValue + 'static>(
+        &self,
+        metric: impl AsRef<str>,
+        tags: impl TagsProvider<S>,
+        callback: impl Fn() -> V + Send + Sync + 'static,
+    ) -> GaugeHandle {
+        match INSTANCE.get() {
+            Some(instance) => {
+                instance.do_register_gauge(self.metric_name(metric.as_ref()), self.merge_tags(&tags), callback)
+            }
+            None => GaugeHandle::inert(),
+        }
+    }
+
+    /// Report a value in a histogram
+    pub fn histogram<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        value: impl MetricValue,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_histogram(self.metric_name(metric.as_ref()), value, self.merge_tags(&tags));
+        }
+    }
+
+    /// Report a value in a distribution
+    pub fn distribution<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        value: impl MetricValue,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_distribution(self.metric_name(metric.as_ref()), value, self.merge_tags(&tags));
+        }
+    }
+
+    /// Report a value in a set
+    pub fn set<S: AsRef<str>>(&self, metric: impl AsRef<str>, value: impl MetricValue, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_set(self.metric_name(metric.as_ref()), value, self.merge_tags(&tags));
+        }
+    }
+
+    /// Make an arbitrary change to a StatsD counter, as of `timestamp`, see [Datadog::count_at]
+    pub fn count_at<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        count: i64,
+        timestamp: SystemTime,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_count_at(
+                self.metric_name(metric.as_ref()),
+                count,
+                timestamp,
+                self.merge_tags(&tags),
+            );
+        }
+    }
+
+    /// Report an arbitrary value as a gauge, as of `timestamp`, see [Datadog::count_at]
+    pub fn gauge_at<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        value: impl MetricValue,
+        timestamp: SystemTime,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_gauge_at(
+                self.metric_name(metric.as_ref()),
+                value,
+                timestamp,
+                self.merge_tags(&tags),
+            );
+        }
+    }
+
+    /// Increment a StatsD counter, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn incr_with_rate<S: AsRef<str>>(&self, metric: impl AsRef<str>, rate: f64, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_incr_with_rate(self.metric_name(metric.as_ref()), rate, self.merge_tags(&tags));
+        }
+    }
+
+    /// Decrement a StatsD counter, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn decr_with_rate<S: AsRef<str>>(&self, metric: impl AsRef<str>, rate: f64, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_decr_with_rate(self.metric_name(metric.as_ref()), rate, self.merge_tags(&tags));
+        }
+    }
+
+    /// Make an arbitrary change to a StatsD counter, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn count_with_rate<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        count: i64,
+        rate: f64,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_count_with_rate(self.metric_name(metric.as_ref()), count, rate, self.merge_tags(&tags));
+        }
+    }
+
+    /// Send your own timing metric in milliseconds, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn timing_with_rate<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        ms: i64,
+        rate: f64,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_timing_with_rate(self.metric_name(metric.as_ref()), ms, rate, self.merge_tags(&tags));
+        }
+    }
+
+    /// Report a value in a histogram, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn histogram_with_rate<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        value: impl MetricValue,
+        rate: f64,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_histogram_with_rate(self.metric_name(metric.as_ref()), value, rate, self.merge_tags(&tags));
+        }
+    }
+
+    /// Report a value in a distribution, sampled at `rate`, see [Datadog::incr_with_rate]
+    pub fn distribution_with_rate<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        value: impl MetricValue,
+        rate: f64,
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_distribution_with_rate(self.metric_name(metric.as_ref()), value, rate, self.merge_tags(&tags));
+        }
+    }
+
+    /// Report several values in a histogram at once, see [Datadog::histogram_many]
+    pub fn histogram_many<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        values: &[impl MetricValue],
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_histogram_many(self.metric_name(metric.as_ref()), values, self.merge_tags(&tags));
+        }
+    }
+
+    /// Report several values in a distribution at once, see [Datadog::histogram_many]
+    pub fn distribution_many<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        values: &[impl MetricValue],
+        tags: impl TagsProvider<S>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_distribution_many(self.metric_name(metric.as_ref()), values, self.merge_tags(&tags));
+        }
+    }
+
+    /// Report the status of a service
+    pub fn service_check<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        value: ServiceStatus,
+        tags: impl TagsProvider<S>,
+        options: Option<ServiceCheckOptions>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_service_check(metric, value, self.merge_tags(&tags), options);
+        }
+    }
+
+    /// Send a custom event as a title and a body
+    pub fn event<S: AsRef<str>>(&self, metric: impl AsRef<str>, text: impl AsRef<str>, tags: impl TagsProvider<S>) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_event(metric, text, self.merge_tags(&tags));
+        }
+    }
+
+    /// Send a custom event as a title, a body and some options
+    pub fn event_with_options<S: AsRef<str>>(
+        &self,
+        metric: impl AsRef<str>,
+        text: impl AsRef<str>,
+        tags: impl TagsProvider<S>,
+        options: Option<EventOptions>,
+    ) {
+        if let Some(instance) = INSTANCE.get() {
+            instance.do_event_with_options(metric, text, self.merge_tags(&tags), options);
+        }
+    }
+}
+
+impl Datadog<dogstatsd::Client> {
+    /// Creates a [Scope] on the global instance, prepending `prefix.` to the name of every metric
+    /// emitted through it.
+    pub fn scoped(prefix: &str) -> Scope {
+        Scope::new(prefix)
+    }
+}
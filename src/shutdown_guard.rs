@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use crate::Datadog;
+
+/// A guard which shuts down the global instance when dropped, so that the metrics still buffered
+/// by the client are sent before the process exits.
+///
+/// It is meant to be held by `main`:
+///
+/// ```
+/// use std::time::Duration;
+/// use prima_datadog::{configuration::Configuration, Datadog};
+///
+/// fn main() {
+///     Datadog::init(Configuration::new("0.0.0.0:1234", "namespace")).unwrap();
+///     let _guard = Datadog::shutdown_guard(Duration::from_secs(2));
+///
+///     // ...
+/// }
+/// ```
+#[must_use = "the global instance is shut down as soon as the guard is dropped"]
+pub struct ShutdownGuard {
+    timeout: Duration,
+}
+
+impl ShutdownGuard {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        let _ = Datadog::shutdown(self.timeout);
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+
+use crate::configuration::BatchingOptions;
+
+use super::{transport::Transport, MetricType};
+
+/// Buffers the datagrams, sending them joined by '\n' when the buffer is full or every `max_time`.
+///
+/// The buffer never exceeds the maximum payload size of the transport, even if `max_buffer_size` is
+/// larger, so that the batches are split on line boundaries rather than truncated.
+#[derive(Debug)]
+pub(crate) struct BatchBuffer {
+    transport: Transport,
+    options: BatchingOptions,
+    max_size: usize,
+    buffer: Vec<u8>,
+    /// The first metric in the buffer, reported to the error handler if the buffer can't be sent
+    first: Option<(String, MetricType)>,
+    last_sent: Instant,
+}
+
+impl BatchBuffer {
+    pub(crate) fn new(transport: Transport, options: BatchingOptions) -> Self {
+        let max_size = options.max_buffer_size.min(transport.max_payload_size());
+        Self {
+            transport,
+            max_size,
+            buffer: Vec::with_capacity(max_size),
+            options,
+            first: None,
+            last_sent: Instant::now(),
+        }
+    }
+
+    /// When the buffer has to be sent even if it is not full
+    pub(crate) fn deadline(&self) -> Instant {
+        self.last_sent + self.options.max_time
+    }
+
+    pub(crate) fn push(&mut self, payload: &[u8], metric: &str, metric_type: MetricType) {
+        if payload.len() > self.max_size {
+            self.transport.drop_oversized(payload, metric, metric_type);
+            return;
+        }
+        if !self.buffer.is_empty() && self.buffer.len() + 1 + payload.len() > self.max_size {
+            self.send();
+        }
+        if self.buffer.is_empty() {
+            self.first = Some((metric.to_string(), metric_type));
+        } else {
+            self.buffer.push(b'\n');
+        }
+        self.buffer.extend_from_slice(payload);
+        if self.buffer.len() >= self.max_size {
+            self.send();
+        }
+    }
+
+    /// Sends the buffer, retrying with an exponential backoff. The buffer is dropped if every
+    /// attempt fails.
+    pub(crate) fn send(&mut self) {
+        self.last_sent = Instant::now();
+        if self.buffer.is_empty() {
+            return;
+        }
+        let (metric, metric_type) = self.first.take().unwrap_or((String::new(), MetricType::Count));
+        self.transport.send_with_retries(
+            &self.buffer,
+            &metric,
+            metric_type,
+            self.options.max_retry_attempts,
+            Duration::from_millis(self.options.initial_retry_delay),
+        );
+        self.buffer.clear();
+    }
+}
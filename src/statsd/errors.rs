@@ -0,0 +1,92 @@
+use std::{
+    io,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use dogstatsd::DogstatsdError;
+
+use crate::{configuration::ErrorHandler, error::Error};
+
+use super::MetricType;
+
+/// Calls the configured [ErrorHandler] at most once per interval
+pub(crate) struct ErrorReporter {
+    handler: ErrorHandler,
+    interval: Duration,
+    start: Instant,
+    /// Milliseconds since `start` of the last call to the handler, plus one (0 means never)
+    last_report: AtomicU64,
+}
+
+impl std::fmt::Debug for ErrorReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ErrorReporter")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ErrorReporter {
+    pub(crate) fn new(handler: ErrorHandler, interval: Duration) -> Self {
+        Self {
+            handler,
+            interval,
+            start: Instant::now(),
+            last_report: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn report(&self, error: io::Error, metric: &str, metric_type: MetricType) {
+        let now = self.start.elapsed().as_millis() as u64 + 1;
+        let last_report = self.last_report.load(Ordering::Relaxed);
+        // Another thread may have reported after `now` was taken
+        if last_report != 0 && now.saturating_sub(last_report) < self.interval.as_millis() as u64 {
+            return;
+        }
+        // Only one of the threads failing at the same time reports the error
+        if self
+            .last_report
+            .compare_exchange(last_report, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            (self.handler)(&Error::from(DogstatsdError::from(error)), metric, metric_type);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_report_is_rate_limited() {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let handler_reported = reported.clone();
+        let reporter = ErrorReporter::new(
+            Arc::new(move |error: &Error, metric: &str, metric_type: MetricType| {
+                handler_reported
+                    .lock()
+                    .unwrap()
+                    .push((error.to_string(), metric.to_string(), metric_type));
+            }),
+            Duration::from_millis(50),
+        );
+
+        let error = || io::Error::other("oh no!");
+        reporter.report(error(), "first", MetricType::Count);
+        reporter.report(error(), "second", MetricType::Gauge);
+        std::thread::sleep(Duration::from_millis(60));
+        reporter.report(error(), "third", MetricType::Event);
+
+        assert_eq!(
+            *reported.lock().unwrap(),
+            vec![
+                ("oh no!".to_string(), "first".to_string(), MetricType::Count),
+                ("oh no!".to_string(), "third".to_string(), MetricType::Event),
+            ]
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! Serialization of metrics, events and service checks to the DogStatsD datagram format
+//!
+//! See <https://docs.datadoghq.com/developers/dogstatsd/datagram_shell/>
+
+use crate::{EventOptions, ServiceCheckOptions, ServiceStatus};
+
+/// The maximum length of the values packed in a single line, so that the line fits a UDP packet
+pub(crate) const MAX_PACKED_VALUES_LEN: usize = 1024;
+
+/// The type of a datagram sent to the agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricType {
+    /// Emitted by `incr`, `decr` and `count`
+    Count,
+    /// Emitted by `timing`, `time` and `async_time`
+    Timing,
+    Gauge,
+    Histogram,
+    Distribution,
+    Set,
+    ServiceCheck,
+    Event,
+}
+
+impl MetricType {
+    /// Returns the type as it is written in the datagram
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricType::Count => "c",
+            MetricType::Timing => "ms",
+            MetricType::Gauge => "g",
+            MetricType::Histogram => "h",
+            MetricType::Distribution => "d",
+            MetricType::Set => "s",
+            MetricType::ServiceCheck => "_sc",
+            MetricType::Event => "_e",
+        }
+    }
+
+    /// Whether the metric can be sent with an explicit timestamp, as of the DogStatsD protocol v1.3
+    pub(crate) fn accepts_timestamp(&self) -> bool {
+        matches!(self, MetricType::Count | MetricType::Gauge)
+    }
+
+    /// Whether several values can be packed in a single line, as of the DogStatsD protocol v1.1
+    pub(crate) fn is_packable(&self) -> bool {
+        matches!(self, MetricType::Histogram | MetricType::Distribution)
+    }
+}
+
+/// Formats the datagrams of a client, applying its namespace and default tags
+#[derive(Debug)]
+pub(crate) struct Formatter {
+    namespace: String,
+    /// The default tags, already joined with a ','
+    default_tags: String,
+    /// The origin detection fields, `|c:container_id|e:external_env`
+    origin: String,
+}
+
+impl Formatter {
+    pub(crate) fn new(namespace: &str, default_tags: &[String]) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            default_tags: default_tags.join(","),
+            origin: String::new(),
+        }
+    }
+
+    /// Appends the origin detection fields to every payload
+    pub(crate) fn with_origin(mut self, origin: String) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// `namespace.metric:value|type|@rate|#tags|c:container_id|e:external_env|Ttimestamp`, the rate
+    /// is omitted when it is 1. The timestamp is only written for the metric types accepting it.
+    pub(crate) fn metric<S: AsRef<str>>(
+        &self,
+        metric: &str,
+        value: &str,
+        metric_type: MetricType,
+        rate: f64,
+        tags: &[S],
+        timestamp: Option<u64>,
+    ) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.namespace.len() + metric.len() + value.len() + 8);
+        if !self.namespace.is_empty() {
+            buf.extend_from_slice(self.namespace.as_bytes());
+            buf.push(b'.');
+        }
+        buf.extend_from_slice(metric.as_bytes());
+        buf.push(b':');
+        buf.extend_from_slice(value.as_bytes());
+        buf.push(b'|');
+        buf.extend_from_slice(metric_type.as_str().as_bytes());
+        if rate < 1.0 {
+            buf.extend_from_slice(format!("|@{rate}").as_bytes());
+        }
+        self.push_tags(&mut buf, tags);
+        buf.extend_from_slice(self.origin.as_bytes());
+        if let Some(timestamp) = timestamp.filter(|_| metric_type.accepts_timestamp()) {
+            buf.extend_from_slice(format!("|T{timestamp}").as_bytes());
+        }
+        buf
+    }
+
+    /// `_sc|name|status|d:timestamp|h:hostname|m:message|#tags|c:container_id|e:external_env`
+    pub(crate) fn service_check(
+        &self,
+        metric: &str,
+        status: ServiceStatus,
+        tags: &[&str],
+        options: Option<ServiceCheckOptions>,
+    ) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(metric.len() + 8);
+        buf.extend_from_slice(b"_sc|");
+        buf.extend_from_slice(metric.as_bytes());
+        buf.push(b'|');
+        buf.extend_from_slice((status as u32).to_string().as_bytes());
+        if let Some(options) = options {
+            if let Some(timestamp) = options.timestamp {
+                push_field(&mut buf, b"d", &timestamp.to_string());
+            }
+            if let Some(hostname) = options.hostname {
+                push_field(&mut buf, b"h", hostname);
+            }
+            if let Some(message) = options.message {
+                push_field(&mut buf, b"m", message);
+            }
+        }
+        self.push_tags(&mut buf, tags);
+        buf.extend_from_slice(self.origin.as_bytes());
+        buf
+    }
+
+    /// `_e{title.length,text.length}:title|text|d:timestamp|h:hostname|k:aggregation_key|p:priority|s:source_type_name|t:alert_type|#tags|c:container_id|e:external_env`
+    pub(crate) fn event(&self, title: &str, text: &str, tags: &[&str], options: Option<EventOptions>) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(title.len() + text.len() + 16);
+        buf.extend_from_slice(format!("_e{{{},{}}}:", title.len(), text.len()).as_bytes());
+        buf.extend_from_slice(title.as_bytes());
+        buf.push(b'|');
+        buf.extend_from_slice(text.as_bytes());
+        if let Some(options) = options {
+            if let Some(timestamp) = options.timestamp {
+                push_field(&mut buf, b"d", &timestamp.to_string());
+            }
+            if let Some(hostname) = options.hostname {
+                push_field(&mut buf, b"h", hostname);
+            }
+            if let Some(aggregation_key) = options.aggregation_key {
+                push_field(&mut buf, b"k", aggregation_key);
+            }
+            if let Some(priority) = options.priority {
+                push_field(&mut buf, b"p", priority.as_str());
+            }
+            if let Some(source_type_name) = options.source_type_name {
+                push_field(&mut buf, b"s", source_type_name);
+            }
+            if let Some(alert_type) = options.alert_type {
+                push_field(&mut buf, b"t", alert_type.as_str());
+            }
+        }
+        self.push_tags(&mut buf, tags);
+        buf.extend_from_slice(self.origin.as_bytes());
+        buf
+    }
+
+    fn push_tags<S: AsRef<str>>(&self, buf: &mut Vec<u8>, tags: &[S]) {
+        if tags.is_empty() && self.default_tags.is_empty() {
+            return;
+        }
+        buf.extend_from_slice(b"|#");
+        for (index, tag) in tags.iter().enumerate() {
+            if index > 0 {
+                buf.push(b',');
+            }
+            buf.extend_from_slice(tag.as_ref().as_bytes());
+        }
+        if !self.default_tags.is_empty() {
+            if !tags.is_empty() {
+                buf.push(b',');
+            }
+            buf.extend_from_slice(self.default_tags.as_bytes());
+        }
+    }
+}
+
+/// Appends `value` to the `packed` values of a line, `1:2:3`. Returns `false`, leaving `packed`
+/// unchanged, if it would exceed [MAX_PACKED_VALUES_LEN].
+pub(crate) fn pack(packed: &mut String, value: &str) -> bool {
+    if packed.is_empty() {
+        packed.push_str(value);
+        return true;
+    }
+    if packed.len() + 1 + value.len() > MAX_PACKED_VALUES_LEN {
+        return false;
+    }
+    packed.push(':');
+    packed.push_str(value);
+    true
+}
+
+fn push_field(buf: &mut Vec<u8>, prefix: &[u8], value: &str) {
+    buf.push(b'|');
+    buf.extend_from_slice(prefix);
+    buf.push(b':');
+    buf.extend_from_slice(value.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventAlertType, EventPriority, EMPTY_TAGS};
+
+    fn format(datagram: Vec<u8>) -> String {
+        String::from_utf8(datagram).unwrap()
+    }
+
+    #[test]
+    fn test_metric() {
+        let formatter = Formatter::new("namespace", &["env:dev".to_string()]);
+
+        assert_eq!(
+            format(formatter.metric("test", "1", MetricType::Count, 1.0, &["a:b", "c:d"], None)),
+            "namespace.test:1|c|#a:b,c:d,env:dev"
+        );
+        assert_eq!(
+            format(formatter.metric("test", "20", MetricType::Timing, 1.0, EMPTY_TAGS, None)),
+            "namespace.test:20|ms|#env:dev"
+        );
+    }
+
+    #[test]
+    fn test_metric_without_namespace_and_tags() {
+        let formatter = Formatter::new("", &[]);
+
+        assert_eq!(
+            format(formatter.metric("test", "1.5", MetricType::Gauge, 1.0, EMPTY_TAGS, None)),
+            "test:1.5|g"
+        );
+    }
+
+    #[test]
+    fn test_metric_with_rate() {
+        let formatter = Formatter::new("", &["env:dev".to_string()]);
+
+        assert_eq!(
+            format(formatter.metric("test", "1", MetricType::Count, 0.25, &["a:b"], None)),
+            "test:1|c|@0.25|#a:b,env:dev"
+        );
+    }
+
+    #[test]
+    fn test_metric_with_timestamp() {
+        let formatter = Formatter::new("", &["env:dev".to_string()]);
+
+        assert_eq!(
+            format(formatter.metric("test", "1.5", MetricType::Gauge, 1.0, EMPTY_TAGS, Some(1656581409))),
+            "test:1.5|g|#env:dev|T1656581409"
+        );
+        // Only counts and gauges accept a timestamp
+        assert_eq!(
+            format(formatter.metric("test", "1", MetricType::Histogram, 1.0, EMPTY_TAGS, Some(1656581409))),
+            "test:1|h|#env:dev"
+        );
+    }
+
+    #[test]
+    fn test_origin() {
+        let formatter = Formatter::new("", &[]).with_origin("|c:abc|e:it-false".to_string());
+
+        assert_eq!(
+            format(formatter.metric("test", "1", MetricType::Count, 1.0, EMPTY_TAGS, Some(1656581409))),
+            "test:1|c|c:abc|e:it-false|T1656581409"
+        );
+        assert_eq!(
+            format(formatter.service_check("test", ServiceStatus::OK, &["a:b"], None)),
+            "_sc|test|0|#a:b|c:abc|e:it-false"
+        );
+    }
+
+    #[test]
+    fn test_pack() {
+        let mut packed = String::new();
+        assert!(pack(&mut packed, "1"));
+        assert!(pack(&mut packed, "2.5"));
+        assert_eq!(packed, "1:2.5");
+
+        let long = "1".repeat(MAX_PACKED_VALUES_LEN - packed.len());
+        assert!(!pack(&mut packed, &long));
+        assert_eq!(packed, "1:2.5");
+    }
+
+    #[test]
+    fn test_service_check() {
+        let formatter = Formatter::new("namespace", &[]);
+        let options = ServiceCheckOptions {
+            timestamp: Some(123),
+            hostname: Some("localhost"),
+            message: Some("message"),
+        };
+
+        assert_eq!(
+            format(formatter.service_check("test", ServiceStatus::Critical, &["key:value"], Some(options))),
+            "_sc|test|2|d:123|h:localhost|m:message|#key:value"
+        );
+    }
+
+    #[test]
+    fn test_event() {
+        let formatter = Formatter::new("namespace", &["env:dev".to_string()]);
+        let options = EventOptions::new()
+            .with_timestamp(1638480000)
+            .with_priority(EventPriority::Low)
+            .with_alert_type(EventAlertType::Error);
+
+        assert_eq!(
+            format(formatter.event("title", "text", EMPTY_TAGS, None)),
+            "_e{5,4}:title|text|#env:dev"
+        );
+        assert_eq!(
+            format(formatter.event("title", "text", &["a:b"], Some(options))),
+            "_e{5,4}:title|text|d:1638480000|p:low|t:error|#a:b,env:dev"
+        );
+    }
+}
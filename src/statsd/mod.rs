@@ -0,0 +1,344 @@
+//! The DogStatsD client used by [Datadog::init](crate::Datadog::init)
+
+mod batch;
+mod errors;
+mod format;
+mod origin;
+mod sampling;
+mod telemetry;
+mod transport;
+mod worker;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+    thread,
+    time::Duration,
+};
+
+use dogstatsd::DogstatsdError;
+
+use crate::{
+    configuration::Configuration, error::Error, ClientStats, DynDogstatsdClient, EventOptions, ServiceCheckOptions,
+    ServiceStatus, EMPTY_TAGS,
+};
+use batch::BatchBuffer;
+use format::Formatter;
+pub use format::MetricType;
+use telemetry::{Reporter, Telemetry};
+use transport::Transport;
+use worker::{Item, Output, Worker};
+
+/// A [DynDogstatsdClient] sending the metrics to the agent over UDP or a unix socket.
+///
+/// By default the metrics are sent on the caller's thread. With
+/// [Configuration::with_worker_options] or [Configuration::with_batching_options] they are pushed
+/// in a bounded queue instead, and serialized and sent by a background thread; then
+/// [DynDogstatsdClient::flush] and [DynDogstatsdClient::shutdown] can be used to make sure that
+/// the pending metrics are sent, e.g. before the process exits.
+///
+/// The values of [DynDogstatsdClient::histogram_many] and [DynDogstatsdClient::distribution_many]
+/// are packed in a single line, e.g. `metric:1:2:3|d`, as supported by the DogStatsD protocol v1.1
+/// (agent 6.25/7.25 or later). With batching, the consecutive histogram and distribution values
+/// of the same metric, tags and rate are packed as well.
+///
+/// The `*_with_rate` methods only send a fraction of the calls, with the `|@rate` field so that the
+/// agent scales the values back up. A default rate can be configured per metric with
+/// [Configuration::with_sample_rate].
+///
+/// The client keeps some internal counters, available through [DynDogstatsdClient::stats] and
+/// periodically reported as `datadog.dogstatsd.client.*` metrics, unless disabled with
+/// [Configuration::without_telemetry].
+///
+/// ```
+/// use prima_datadog::{configuration::Configuration, statsd::StatsdClient, Datadog, TagTrackerConfiguration};
+///
+/// let client = StatsdClient::new(&Configuration::new("0.0.0.0:1234", "namespace")).unwrap();
+/// let datadog = Datadog::new(client, TagTrackerConfiguration::new());
+/// ```
+#[derive(Debug)]
+pub struct StatsdClient {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    formatter: Arc<Formatter>,
+    sink: Sink,
+    telemetry: Arc<Telemetry>,
+    /// The rates of [Configuration::with_sample_rate]
+    sample_rates: HashMap<String, f64>,
+}
+
+#[derive(Debug)]
+enum Sink {
+    Direct(Transport),
+    Worker(Worker),
+}
+
+impl StatsdClient {
+    /// Creates a client using the address, namespace, default tags, socket path, batching, worker,
+    /// telemetry and origin detection options of the configuration.
+    ///
+    /// For origin detection, `DD_ENTITY_ID` is added as the `dd.internal.entity_id` tag and
+    /// `DD_EXTERNAL_ENV` is sent as the `|e:` field of every payload.
+    pub fn new(configuration: &Configuration) -> Result<Self, Error> {
+        let telemetry = Arc::new(Telemetry::new(configuration.tracker_events()));
+        let transport = Transport::new(configuration, telemetry.clone()).map_err(DogstatsdError::from)?;
+        let transport_name = transport.name();
+        let default_tags = configuration.resolved_tags();
+        let origin = origin::fields(configuration);
+        let formatter = Arc::new(Formatter::new(configuration.namespace(), &default_tags).with_origin(origin.clone()));
+        let sink = match (configuration.batching_options(), configuration.worker_options()) {
+            (None, None) => Sink::Direct(transport),
+            (Some(batching_options), worker_options) => Sink::Worker(Worker::new(
+                formatter.clone(),
+                Output::Batched(BatchBuffer::new(transport, batching_options)),
+                worker_options.unwrap_or_default(),
+                telemetry.clone(),
+            )?),
+            (None, Some(worker_options)) => Sink::Worker(Worker::new(
+                formatter.clone(),
+                Output::Direct(transport),
+                worker_options,
+                telemetry.clone(),
+            )?),
+        };
+        let inner = Arc::new(Inner {
+            formatter,
+            sink,
+            telemetry,
+            sample_rates: configuration.sample_rates().clone(),
+        });
+
+        if let Some(interval) = configuration.telemetry_interval() {
+            let mut telemetry_tags = default_tags;
+            telemetry_tags.push("client:rust".to_string());
+            telemetry_tags.push(format!("client_version:{}", env!("CARGO_PKG_VERSION")));
+            telemetry_tags.push(format!("client_transport:{transport_name}"));
+            let formatter = Formatter::new("", &telemetry_tags).with_origin(origin);
+            spawn_reporter(Arc::downgrade(&inner), formatter, interval);
+        }
+
+        Ok(Self { inner })
+    }
+
+    /// The number of metrics dropped because the queue of the background worker was full, or
+    /// because they were emitted after the shutdown
+    pub fn dropped_items(&self) -> u64 {
+        self.inner.telemetry.snapshot().dropped_items
+    }
+
+    /// The rate configured for the metric, used when it is not emitted with an explicit rate
+    fn sample_rate(&self, metric: &str) -> f64 {
+        self.inner.sample_rates.get(metric).copied().unwrap_or(1.0)
+    }
+
+    fn send_metric(&self, metric: &str, value: &str, metric_type: MetricType, rate: f64, tags: &[&str]) {
+        if sampling::sample(rate) {
+            self.send_sampled(metric, value, metric_type, rate, tags, None);
+        }
+    }
+
+    /// Sends the sampled values packed in as few lines as possible, `metric:1:2:3|d`
+    fn send_many(&self, metric: &str, vals: &[&str], metric_type: MetricType, rate: f64, tags: &[&str]) {
+        let mut packed = String::new();
+        for val in vals.iter().filter(|_| sampling::sample(rate)) {
+            if !format::pack(&mut packed, val) {
+                self.send_sampled(metric, &packed, metric_type, rate, tags, None);
+                packed.clear();
+                format::pack(&mut packed, val);
+            }
+        }
+        if !packed.is_empty() {
+            self.send_sampled(metric, &packed, metric_type, rate, tags, None);
+        }
+    }
+
+    fn send_sampled(
+        &self,
+        metric: &str,
+        value: &str,
+        metric_type: MetricType,
+        rate: f64,
+        tags: &[&str],
+        timestamp: Option<u64>,
+    ) {
+        self.inner.telemetry.metric();
+        match &self.inner.sink {
+            Sink::Direct(transport) => transport.send(
+                &self
+                    .inner
+                    .formatter
+                    .metric(metric, value, metric_type, rate, tags, timestamp),
+                metric,
+                metric_type,
+            ),
+            Sink::Worker(worker) => worker.push(Item::Metric {
+                metric: metric.to_string(),
+                value: value.to_string(),
+                metric_type,
+                rate,
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                timestamp,
+            }),
+        }
+    }
+}
+
+impl Inner {
+    fn send(&self, payload: Vec<u8>, metric: &str, metric_type: MetricType) {
+        match &self.sink {
+            Sink::Direct(transport) => transport.send(&payload, metric, metric_type),
+            Sink::Worker(worker) => worker.push(Item::Datagram {
+                payload,
+                metric: metric.to_string(),
+                metric_type,
+            }),
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        match &self.sink {
+            Sink::Direct(_) => false,
+            Sink::Worker(worker) => worker.is_closed(),
+        }
+    }
+}
+
+impl DynDogstatsdClient for StatsdClient {
+    fn incr(&self, metric: &str, tags: &[&str]) {
+        self.incr_with_rate(metric, self.sample_rate(metric), tags);
+    }
+
+    fn decr(&self, metric: &str, tags: &[&str]) {
+        self.decr_with_rate(metric, self.sample_rate(metric), tags);
+    }
+
+    fn count(&self, metric: &str, count: i64, tags: &[&str]) {
+        self.count_with_rate(metric, count, self.sample_rate(metric), tags);
+    }
+
+    fn timing(&self, metric: &str, ms: i64, tags: &[&str]) {
+        self.timing_with_rate(metric, ms, self.sample_rate(metric), tags);
+    }
+
+    fn timing_value(&self, metric: &str, val: &str, tags: &[&str]) {
+        self.send_metric(metric, val, MetricType::Timing, self.sample_rate(metric), tags);
+    }
+
+    fn gauge(&self, metric: &str, val: &str, tags: &[&str]) {
+        self.send_metric(metric, val, MetricType::Gauge, 1.0, tags);
+    }
+
+    fn histogram(&self, metric: &str, val: &str, tags: &[&str]) {
+        self.histogram_with_rate(metric, val, self.sample_rate(metric), tags);
+    }
+
+    fn distribution(&self, metric: &str, val: &str, tags: &[&str]) {
+        self.distribution_with_rate(metric, val, self.sample_rate(metric), tags);
+    }
+
+    fn set(&self, metric: &str, val: &str, tags: &[&str]) {
+        self.send_metric(metric, val, MetricType::Set, 1.0, tags);
+    }
+
+    fn incr_with_rate(&self, metric: &str, rate: f64, tags: &[&str]) {
+        self.send_metric(metric, "1", MetricType::Count, rate, tags);
+    }
+
+    fn decr_with_rate(&self, metric: &str, rate: f64, tags: &[&str]) {
+        self.send_metric(metric, "-1", MetricType::Count, rate, tags);
+    }
+
+    fn count_with_rate(&self, metric: &str, count: i64, rate: f64, tags: &[&str]) {
+        self.send_metric(metric, &count.to_string(), MetricType::Count, rate, tags);
+    }
+
+    fn timing_with_rate(&self, metric: &str, ms: i64, rate: f64, tags: &[&str]) {
+        self.send_metric(metric, &ms.to_string(), MetricType::Timing, rate, tags);
+    }
+
+    fn histogram_with_rate(&self, metric: &str, val: &str, rate: f64, tags: &[&str]) {
+        self.send_metric(metric, val, MetricType::Histogram, rate, tags);
+    }
+
+    fn distribution_with_rate(&self, metric: &str, val: &str, rate: f64, tags: &[&str]) {
+        self.send_metric(metric, val, MetricType::Distribution, rate, tags);
+    }
+
+    fn count_at(&self, metric: &str, count: i64, timestamp: u64, tags: &[&str]) {
+        self.send_sampled(
+            metric,
+            &count.to_string(),
+            MetricType::Count,
+            1.0,
+            tags,
+            Some(timestamp),
+        );
+    }
+
+    fn gauge_at(&self, metric: &str, val: &str, timestamp: u64, tags: &[&str]) {
+        self.send_sampled(metric, val, MetricType::Gauge, 1.0, tags, Some(timestamp));
+    }
+
+    fn histogram_many(&self, metric: &str, vals: &[&str], tags: &[&str]) {
+        self.send_many(metric, vals, MetricType::Histogram, self.sample_rate(metric), tags);
+    }
+
+    fn distribution_many(&self, metric: &str, vals: &[&str], tags: &[&str]) {
+        self.send_many(metric, vals, MetricType::Distribution, self.sample_rate(metric), tags);
+    }
+
+    fn service_check(&self, metric: &str, val: ServiceStatus, tags: &[&str], options: Option<ServiceCheckOptions>) {
+        self.inner.telemetry.service_check();
+        let payload = self.inner.formatter.service_check(metric, val, tags, options);
+        self.inner.send(payload, metric, MetricType::ServiceCheck);
+    }
+
+    fn event(&self, title: &str, text: &str, tags: &[&str]) {
+        self.event_with_options(title, text, tags, None);
+    }
+
+    fn event_with_options(&self, title: &str, text: &str, tags: &[&str], options: Option<EventOptions>) {
+        self.inner.telemetry.event();
+        let payload = self.inner.formatter.event(title, text, tags, options);
+        self.inner.send(payload, title, MetricType::Event);
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        match &self.inner.sink {
+            Sink::Direct(_) => Ok(()),
+            Sink::Worker(worker) => worker.flush(),
+        }
+    }
+
+    fn shutdown(&self, timeout: Duration) -> Result<(), Error> {
+        match &self.inner.sink {
+            Sink::Direct(_) => Ok(()),
+            Sink::Worker(worker) => worker.shutdown(timeout),
+        }
+    }
+
+    fn stats(&self) -> ClientStats {
+        self.inner.telemetry.snapshot()
+    }
+}
+
+/// Periodically reports the telemetry, until the client is dropped or shut down
+fn spawn_reporter(inner: Weak<Inner>, formatter: Formatter, interval: Duration) {
+    let mut reporter = Reporter::default();
+    let _ = thread::Builder::new()
+        .name("prima-datadog-telemetry".to_string())
+        .spawn(move || loop {
+            thread::sleep(interval);
+            let inner = match inner.upgrade() {
+                Some(inner) if !inner.is_closed() => inner,
+                _ => return,
+            };
+            for (metric, value) in reporter.report(&inner.telemetry) {
+                let payload = formatter.metric(&metric, &value.to_string(), MetricType::Count, 1.0, EMPTY_TAGS, None);
+                inner.send(payload, &metric, MetricType::Count);
+            }
+        });
+}
@@ -0,0 +1,192 @@
+//! Origin detection: the container ID and external environment sent with every payload, so that
+//! the agent can tag the metrics with the pod and container they come from
+//!
+//! See <https://docs.datadoghq.com/developers/dogstatsd/?tab=kubernetes#origin-detection-over-udp>
+
+use std::fs;
+
+use crate::configuration::Configuration;
+
+const CGROUP_PATH: &str = "/proc/self/cgroup";
+const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+/// The `|c:container_id|e:external_env` fields appended to every payload, empty if there is none
+pub(crate) fn fields(configuration: &Configuration) -> String {
+    let mut fields = String::new();
+    if let Some(container_id) = container_id(configuration) {
+        fields.push_str("|c:");
+        fields.push_str(&container_id);
+    }
+    if let Some(external_env) = std::env::var("DD_EXTERNAL_ENV").ok().and_then(|env| sanitize(&env)) {
+        fields.push_str("|e:");
+        fields.push_str(&external_env);
+    }
+    fields
+}
+
+/// The configured container ID, or the detected one if origin detection is enabled and the origin
+/// is not already given by `DD_ENTITY_ID`
+fn container_id(configuration: &Configuration) -> Option<String> {
+    if let Some(container_id) = configuration.container_id() {
+        return Some(container_id.to_string());
+    }
+    if !configuration.origin_detection() || std::env::var_os("DD_ENTITY_ID").is_some() {
+        return None;
+    }
+    let from_cgroup = fs::read_to_string(CGROUP_PATH)
+        .ok()
+        .and_then(|cgroup| container_id_from_cgroup(&cgroup));
+    from_cgroup.or_else(|| {
+        fs::read_to_string(MOUNTINFO_PATH)
+            .ok()
+            .and_then(|mountinfo| container_id_from_mountinfo(&mountinfo))
+    })
+}
+
+/// Keeps the printable characters, except the `|` field separator
+fn sanitize(value: &str) -> Option<String> {
+    let value: String = value
+        .trim()
+        .chars()
+        .filter(|c| c.is_ascii_graphic() && *c != '|')
+        .collect();
+    Some(value).filter(|value| !value.is_empty())
+}
+
+/// Finds the container ID at the end of a path of `/proc/self/cgroup`, whose lines are
+/// `hierarchy:controllers:path`. With cgroup v2 the path is usually `/` inside a container, then
+/// the ID has to be found in `/proc/self/mountinfo`.
+pub(crate) fn container_id_from_cgroup(cgroup: &str) -> Option<String> {
+    cgroup.lines().find_map(|line| {
+        let mut parts = line.splitn(3, ':');
+        let hierarchy = parts.next()?;
+        let path = parts.nth(1)?;
+        if hierarchy.is_empty() || !hierarchy.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let path = path.strip_suffix(".scope").unwrap_or(path);
+        container_id_suffix(path).map(str::to_string)
+    })
+}
+
+/// Finds the container ID in the source of the `hostname` mount in `/proc/self/mountinfo`, e.g.
+/// `/var/lib/docker/containers/<id>/hostname`. The `sandboxes` directories of containerd belong to
+/// the pause container of the pod, so they are skipped.
+pub(crate) fn container_id_from_mountinfo(mountinfo: &str) -> Option<String> {
+    mountinfo
+        .lines()
+        .flat_map(str::split_whitespace)
+        .filter(|field| field.contains("hostname"))
+        .find_map(|field| {
+            let segments: Vec<&str> = field.split('/').collect();
+            (1..segments.len().saturating_sub(1)).find_map(|index| {
+                let id = segments[index];
+                let parent = segments[index - 1];
+                let rest = &segments[index + 1..];
+                let is_id = is_hex(id, 64) || is_uuid(id);
+                let in_hostname_mount = rest.iter().any(|segment| segment.contains("hostname"));
+                (is_id && in_hostname_mount && !parent.is_empty() && parent != "sandboxes").then(|| id.to_string())
+            })
+        })
+}
+
+/// A container ID (64 hex digits), a UUID, or an ECS Fargate task ID (`<32 hex digits>-<digits>`)
+/// at the end of the path
+fn container_id_suffix(path: &str) -> Option<&str> {
+    let suffix = |len: usize| path.len().checked_sub(len).and_then(|start| path.get(start..));
+    if let Some(id) = suffix(64).filter(|id| is_hex(id, 64)) {
+        return Some(id);
+    }
+    if let Some(id) = suffix(36).filter(|id| is_uuid(id)) {
+        return Some(id);
+    }
+    let (task, digits) = path.rsplit_once('-')?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let start = task.len().checked_sub(32)?;
+    task.get(start..)
+        .filter(|task| is_hex(task, 32))
+        .and_then(|_| path.get(start..))
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len && value.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// `8-4-4-4-12` hex digits, separated by `-` or by `_` as in the systemd slices
+fn is_uuid(value: &str) -> bool {
+    value.len() == 36
+        && value.bytes().enumerate().all(|(index, b)| match index {
+            8 | 13 | 18 | 23 => b == b'-' || b == b'_',
+            _ => b.is_ascii_digit() || (b'a'..=b'f').contains(&b),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/origin/{}", env!("CARGO_MANIFEST_DIR"), name);
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_cgroup_v1() {
+        assert_eq!(
+            container_id_from_cgroup(&fixture("cgroup_v1_docker")).as_deref(),
+            Some("3726184226f5d3147c25fdeab5b60097e378e8a720503a5e19ecfdf29f869860")
+        );
+        assert_eq!(
+            container_id_from_cgroup(&fixture("cgroup_v1_kubernetes")).as_deref(),
+            Some("7b8952daecf4c0e44bbcefe1b5c5ebc7b4839d4eefeccefe694709d3809b6199")
+        );
+        assert_eq!(
+            container_id_from_cgroup(&fixture("cgroup_v1_ecs_fargate")).as_deref(),
+            Some("34dc0b5e626f2c5c4c5170e34b10e765-1234567890")
+        );
+    }
+
+    #[test]
+    fn test_cgroup_v2() {
+        assert_eq!(container_id_from_cgroup(&fixture("cgroup_v2")), None);
+        assert_eq!(
+            container_id_from_cgroup(
+                "0::/system.slice/docker-0cfa82bf3ab29da271548d6a044e95c948c6fd2f7578fb41833a44ca23da425f.scope"
+            )
+            .as_deref(),
+            Some("0cfa82bf3ab29da271548d6a044e95c948c6fd2f7578fb41833a44ca23da425f")
+        );
+    }
+
+    #[test]
+    fn test_mountinfo() {
+        assert_eq!(
+            container_id_from_mountinfo(&fixture("mountinfo_docker")).as_deref(),
+            Some("0cfa82bf3ab29da271548d6a044e95c948c6fd2f7578fb41833a44ca23da425f")
+        );
+        assert_eq!(
+            container_id_from_mountinfo(&fixture("mountinfo_crio")).as_deref(),
+            Some("1d3fa3b2b2bca6c5d1a0e4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2b3c4d5")
+        );
+        assert_eq!(
+            container_id_from_mountinfo(&fixture("mountinfo_containerd_sandbox")),
+            None
+        );
+        assert_eq!(container_id_from_mountinfo(&fixture("mountinfo_host")), None);
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize(" it-false,cn-app|x ").as_deref(), Some("it-false,cn-appx"));
+        assert_eq!(sanitize("  "), None);
+    }
+
+    #[test]
+    fn test_configured_container_id() {
+        let configuration = Configuration::new("127.0.0.1:8125", "").with_container_id("abc123");
+
+        assert!(fields(&configuration).starts_with("|c:abc123"));
+    }
+}
@@ -0,0 +1,41 @@
+use std::{
+    cell::Cell,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+thread_local! {
+    /// The state of a xorshift64* generator: sampling doesn't need a better one
+    static STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+}
+
+/// Whether a metric sampled at `rate` has to be sent
+pub(crate) fn sample(rate: f64) -> bool {
+    if rate >= 1.0 || rate.is_nan() {
+        return true;
+    }
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        // The 53 high bits give a uniform float in [0, 1)
+        let random = (x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 / (1u64 << 53) as f64;
+        random < rate
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        assert!((0..1000).all(|_| sample(1.0)));
+        assert!((0..1000).all(|_| !sample(0.0)));
+
+        let sent = (0..10_000).filter(|_| sample(0.25)).count();
+        assert!((2_000..3_000).contains(&sent), "{} sampled metrics", sent);
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::ClientStats;
+
+/// The prefix of the metrics reporting the telemetry, shared with the official Datadog clients
+const PREFIX: &str = "datadog.dogstatsd.client";
+
+/// The internal counters of a client
+#[derive(Debug, Default)]
+pub(crate) struct Telemetry {
+    metrics: AtomicU64,
+    events: AtomicU64,
+    service_checks: AtomicU64,
+    packets_sent: AtomicU64,
+    bytes_sent: AtomicU64,
+    packets_dropped: AtomicU64,
+    bytes_dropped: AtomicU64,
+    packets_dropped_queue: AtomicU64,
+    oversized_payloads: AtomicU64,
+    /// The actions taken by the tag tracker, counted by the tracker itself
+    tracker_events: Arc<AtomicU64>,
+}
+
+impl Telemetry {
+    pub(crate) fn new(tracker_events: Arc<AtomicU64>) -> Self {
+        Self {
+            tracker_events,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn metric(&self) {
+        self.metrics.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn event(&self) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn service_check(&self) {
+        self.service_checks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the outcome of sending a packet of `bytes` to the agent
+    pub(crate) fn packet(&self, bytes: usize, sent: bool) {
+        let (packets, total_bytes) = if sent {
+            (&self.packets_sent, &self.bytes_sent)
+        } else {
+            (&self.packets_dropped, &self.bytes_dropped)
+        };
+        packets.fetch_add(1, Ordering::Relaxed);
+        total_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Records an item dropped by the queue of the background worker
+    pub(crate) fn dropped_by_queue(&self) {
+        self.packets_dropped_queue.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a payload dropped because it can't fit in a single packet
+    pub(crate) fn oversized_payload(&self) {
+        self.oversized_payloads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ClientStats {
+        ClientStats {
+            metrics: self.metrics.load(Ordering::Relaxed),
+            events: self.events.load(Ordering::Relaxed),
+            service_checks: self.service_checks.load(Ordering::Relaxed),
+            packets_sent: self.packets_sent.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            packets_dropped: self.packets_dropped.load(Ordering::Relaxed),
+            bytes_dropped: self.bytes_dropped.load(Ordering::Relaxed),
+            dropped_items: self.packets_dropped_queue.load(Ordering::Relaxed),
+            oversized_payloads: self.oversized_payloads.load(Ordering::Relaxed),
+            tracker_events: self.tracker_events.load(Ordering::Relaxed),
+            invalid_values: 0,
+            gauge_callback_panics: 0,
+        }
+    }
+}
+
+/// Computes the telemetry metrics to report, as the difference with the previous report
+#[derive(Debug, Default)]
+pub(crate) struct Reporter {
+    last: ClientStats,
+}
+
+impl Reporter {
+    /// Returns the name and the value of every counter which changed since the last report
+    pub(crate) fn report(&mut self, telemetry: &Telemetry) -> Vec<(String, u64)> {
+        let current = telemetry.snapshot();
+        let counters = [
+            ("metrics", current.metrics, self.last.metrics),
+            ("events", current.events, self.last.events),
+            ("service_checks", current.service_checks, self.last.service_checks),
+            ("packets_sent", current.packets_sent, self.last.packets_sent),
+            ("bytes_sent", current.bytes_sent, self.last.bytes_sent),
+            ("packets_dropped", current.packets_dropped, self.last.packets_dropped),
+            ("bytes_dropped", current.bytes_dropped, self.last.bytes_dropped),
+            ("packets_dropped_queue", current.dropped_items, self.last.dropped_items),
+            (
+                "packets_dropped_oversized",
+                current.oversized_payloads,
+                self.last.oversized_payloads,
+            ),
+            ("tracker_events", current.tracker_events, self.last.tracker_events),
+        ];
+        self.last = current;
+        counters
+            .iter()
+            .filter(|(_, current, last)| current != last)
+            .map(|(name, current, last)| (format!("{PREFIX}.{name}"), current - last))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_only_sends_the_changes() {
+        let telemetry = Telemetry::default();
+        let mut reporter = Reporter::default();
+        telemetry.metric();
+        telemetry.metric();
+        telemetry.packet(10, true);
+        telemetry.packet(4, false);
+
+        assert_eq!(
+            reporter.report(&telemetry),
+            vec![
+                ("datadog.dogstatsd.client.metrics".to_string(), 2),
+                ("datadog.dogstatsd.client.packets_sent".to_string(), 1),
+                ("datadog.dogstatsd.client.bytes_sent".to_string(), 10),
+                ("datadog.dogstatsd.client.packets_dropped".to_string(), 1),
+                ("datadog.dogstatsd.client.bytes_dropped".to_string(), 4),
+            ]
+        );
+
+        telemetry.event();
+        assert_eq!(
+            reporter.report(&telemetry),
+            vec![("datadog.dogstatsd.client.events".to_string(), 1)]
+        );
+        assert!(reporter.report(&telemetry).is_empty());
+    }
+}
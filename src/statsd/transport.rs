@@ -0,0 +1,190 @@
+use std::{
+    convert::TryFrom,
+    io::{self, Write},
+    net::UdpSocket,
+    os::unix::net::{UnixDatagram, UnixStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::configuration::{Configuration, SocketType};
+
+use super::{errors::ErrorReporter, telemetry::Telemetry, MetricType};
+
+/// The default maximum payload size over UDP, fitting the usual MTU of 1500 bytes once the IP and
+/// UDP headers are removed
+const DEFAULT_UDP_MAX_PAYLOAD_SIZE: usize = 1432;
+
+/// The default maximum payload size over a unix socket, the default buffer size of the agent
+const DEFAULT_UDS_MAX_PAYLOAD_SIZE: usize = 8192;
+
+/// How long a write on a stream socket can block, as in the official clients
+const STREAM_WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The socket used to send datagrams to the agent
+#[derive(Debug)]
+pub(crate) struct Transport {
+    socket: Socket,
+    /// The payloads larger than this are dropped rather than truncated or rejected by the OS
+    max_payload_size: usize,
+    telemetry: Arc<Telemetry>,
+    errors: Option<ErrorReporter>,
+}
+
+#[derive(Debug)]
+enum Socket {
+    Udp {
+        socket: UdpSocket,
+        to_addr: String,
+    },
+    /// The socket is not connected, so that the agent can be (re)started after the client
+    Uds {
+        socket: UnixDatagram,
+        path: PathBuf,
+    },
+    /// Connected on the first send, and again after a failed send, e.g. when the agent restarts
+    UdsStream {
+        stream: Mutex<Option<UnixStream>>,
+        path: PathBuf,
+    },
+}
+
+impl Transport {
+    pub(crate) fn new(configuration: &Configuration, telemetry: Arc<Telemetry>) -> io::Result<Self> {
+        let socket_path = configuration
+            .socket_path()
+            .filter(|path| !configuration.udp_fallback() || Path::new(path).exists());
+        let socket = match (socket_path, configuration.socket_type()) {
+            (Some(path), SocketType::Datagram) => Socket::Uds {
+                socket: UnixDatagram::unbound()?,
+                path: PathBuf::from(path),
+            },
+            (Some(path), SocketType::Stream) => Socket::UdsStream {
+                stream: Mutex::new(None),
+                path: PathBuf::from(path),
+            },
+            (None, _) => Socket::Udp {
+                socket: UdpSocket::bind(configuration.from_addr())?,
+                to_addr: configuration.to_addr().to_string(),
+            },
+        };
+        let max_payload_size = configuration.max_payload_size().unwrap_or(match socket {
+            Socket::Udp { .. } => DEFAULT_UDP_MAX_PAYLOAD_SIZE,
+            Socket::Uds { .. } | Socket::UdsStream { .. } => DEFAULT_UDS_MAX_PAYLOAD_SIZE,
+        });
+        let errors = configuration
+            .error_handler()
+            .map(|handler| ErrorReporter::new(handler, configuration.error_rate_limit()));
+        Ok(Self {
+            socket,
+            max_payload_size,
+            telemetry,
+            errors,
+        })
+    }
+
+    /// The name of the transport, as reported by the telemetry
+    pub(crate) fn name(&self) -> &'static str {
+        match self.socket {
+            Socket::Udp { .. } => "udp",
+            Socket::Uds { .. } => "uds",
+            Socket::UdsStream { .. } => "uds-stream",
+        }
+    }
+
+    pub(crate) fn max_payload_size(&self) -> usize {
+        self.max_payload_size
+    }
+
+    /// Sends the payload, whose (first) metric is used to report a failure to the error handler
+    pub(crate) fn send(&self, payload: &[u8], metric: &str, metric_type: MetricType) {
+        if payload.len() > self.max_payload_size {
+            self.drop_oversized(payload, metric, metric_type);
+            return;
+        }
+        let result = self.try_send(payload);
+        self.record(payload, result, metric, metric_type);
+    }
+
+    /// Same as [Transport::send], retrying at most `max_retry_attempts` times with an exponential
+    /// backoff
+    pub(crate) fn send_with_retries(
+        &self,
+        payload: &[u8],
+        metric: &str,
+        metric_type: MetricType,
+        max_retry_attempts: usize,
+        initial_retry_delay: Duration,
+    ) {
+        let mut delay = initial_retry_delay;
+        let mut result = self.try_send(payload);
+        for _ in 0..max_retry_attempts {
+            if result.is_ok() {
+                break;
+            }
+            thread::sleep(delay);
+            delay *= 2;
+            result = self.try_send(payload);
+        }
+        self.record(payload, result, metric, metric_type);
+    }
+
+    /// Drops a payload which can't fit in a single packet, counting it and reporting it to the error
+    /// handler
+    pub(crate) fn drop_oversized(&self, payload: &[u8], metric: &str, metric_type: MetricType) {
+        self.telemetry.oversized_payload();
+        if let Some(errors) = &self.errors {
+            let error = io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "payload of {} bytes exceeds the maximum payload size of {} bytes",
+                    payload.len(),
+                    self.max_payload_size
+                ),
+            );
+            errors.report(error, metric, metric_type);
+        }
+    }
+
+    fn record(&self, payload: &[u8], result: io::Result<()>, metric: &str, metric_type: MetricType) {
+        self.telemetry.packet(payload.len(), result.is_ok());
+        if let (Err(error), Some(errors)) = (result, &self.errors) {
+            errors.report(error, metric, metric_type);
+        }
+    }
+
+    fn try_send(&self, payload: &[u8]) -> io::Result<()> {
+        match &self.socket {
+            Socket::Udp { socket, to_addr } => socket.send_to(payload, to_addr.as_str()).map(|_| ()),
+            Socket::Uds { socket, path } => socket.send_to(payload, path).map(|_| ()),
+            Socket::UdsStream { stream, path } => {
+                let mut stream = stream.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                send_frame(&mut stream, path, payload)
+            }
+        }
+    }
+}
+
+/// Writes the payload prefixed by its length, connecting first if needed. A failed write on an
+/// existing connection, e.g. after the agent restarted, is retried once on a new connection.
+fn send_frame(stream: &mut Option<UnixStream>, path: &Path, payload: &[u8]) -> io::Result<()> {
+    let length = u32::try_from(payload.len()).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&length.to_le_bytes());
+    frame.extend_from_slice(payload);
+    // A partial write would corrupt the stream, so a connection is only kept after a successful
+    // write
+    if let Some(mut connected) = stream.take() {
+        if connected.write_all(&frame).is_ok() {
+            *stream = Some(connected);
+            return Ok(());
+        }
+    }
+    let mut connected = UnixStream::connect(path)?;
+    connected.set_write_timeout(Some(STREAM_WRITE_TIMEOUT))?;
+    connected.write_all(&frame)?;
+    *stream = Some(connected);
+    Ok(())
+}
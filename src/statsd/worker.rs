@@ -0,0 +1,640 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    thread::{self, JoinHandle, Thread},
+    time::{Duration, Instant},
+};
+
+use crossbeam_queue::ArrayQueue;
+use dogstatsd::DogstatsdError;
+
+use crate::{
+    configuration::{OverflowPolicy, WorkerOptions},
+    error::Error,
+};
+
+use super::{
+    batch::BatchBuffer,
+    format::{self, Formatter},
+    telemetry::Telemetry,
+    transport::Transport,
+    MetricType,
+};
+
+/// How long the worker sleeps when there is nothing to do and no batch to send
+const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long a caller blocked by a full queue waits before trying again, in case it missed the
+/// notification of the worker
+const BLOCKED_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// An item waiting in the queue of the worker
+pub(crate) enum Item {
+    /// A metric, serialized by the worker
+    Metric {
+        metric: String,
+        value: String,
+        metric_type: MetricType,
+        rate: f64,
+        tags: Vec<String>,
+        /// Only set for counts and gauges
+        timestamp: Option<u64>,
+    },
+    /// Events and service checks are rare, so they are serialized by the caller
+    Datagram {
+        payload: Vec<u8>,
+        metric: String,
+        metric_type: MetricType,
+    },
+}
+
+enum Control {
+    /// Sends everything enqueued so far, then notifies the sender
+    Flush(Sender<()>),
+    /// Sends everything enqueued so far, notifies the sender and stops the worker
+    Shutdown(Sender<()>),
+}
+
+/// Where the worker writes the serialized datagrams
+#[derive(Debug)]
+pub(crate) enum Output {
+    Direct(Transport),
+    Batched(BatchBuffer),
+}
+
+impl Output {
+    fn push(&mut self, payload: &[u8], metric: &str, metric_type: MetricType) {
+        match self {
+            Output::Direct(transport) => transport.send(payload, metric, metric_type),
+            Output::Batched(batch) => batch.push(payload, metric, metric_type),
+        }
+    }
+
+    fn send(&mut self) {
+        if let Output::Batched(batch) = self {
+            batch.send();
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        match self {
+            Output::Direct(_) => None,
+            Output::Batched(batch) => Some(batch.deadline()),
+        }
+    }
+}
+
+/// State shared between the handle and the worker thread
+struct Shared {
+    queue: ArrayQueue<Item>,
+    overflow_policy: OverflowPolicy,
+    telemetry: Arc<Telemetry>,
+    /// Set once the worker has been shut down, after which every item is dropped
+    closed: AtomicBool,
+    /// Notified by the worker after a drain, to wake up the callers blocked by a full queue
+    room: Condvar,
+    blocked: Mutex<()>,
+    /// Set by the worker right before parking, so that the callers only wake it up when needed
+    parked: AtomicBool,
+    /// How many queued items wake the worker up: one when sending right away, more when batching
+    /// since the batch is only sent on its deadline anyway
+    wake_threshold: usize,
+    /// Set by a flush requested from the worker thread itself, which can't wait for its own answer
+    flush_requested: AtomicBool,
+}
+
+/// A background thread which serializes and sends the items pushed in a bounded lock-free queue
+#[derive(Debug)]
+pub(crate) struct Worker {
+    shared: Arc<Shared>,
+    thread: Thread,
+    control: Mutex<Option<Sender<Control>>>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl std::fmt::Debug for Shared {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shared")
+            .field("queued", &self.queue.len())
+            .field("overflow_policy", &self.overflow_policy)
+            .field("closed", &self.closed)
+            .finish()
+    }
+}
+
+impl Shared {
+    fn new(options: WorkerOptions, wake_threshold: usize, telemetry: Arc<Telemetry>) -> Self {
+        let capacity = options.queue_capacity.max(1);
+        Self {
+            queue: ArrayQueue::new(capacity),
+            overflow_policy: options.overflow_policy,
+            telemetry,
+            closed: AtomicBool::new(false),
+            room: Condvar::new(),
+            blocked: Mutex::new(()),
+            parked: AtomicBool::new(false),
+            wake_threshold: wake_threshold.clamp(1, capacity),
+            flush_requested: AtomicBool::new(false),
+        }
+    }
+
+    /// Enqueues an item, waking the worker up if it is parked and enough items are queued
+    fn push(&self, item: Item, worker: &Thread) {
+        if self.closed.load(Ordering::Acquire) {
+            self.drop_item();
+            return;
+        }
+        match self.overflow_policy {
+            OverflowPolicy::DropNewest => {
+                if self.queue.push(item).is_err() {
+                    self.drop_item();
+                }
+            }
+            OverflowPolicy::DropOldest => {
+                if self.queue.force_push(item).is_some() {
+                    self.drop_item();
+                }
+            }
+            OverflowPolicy::Block => {
+                if let Err(rejected) = self.queue.push(item) {
+                    // Only the worker makes room, so it can't wait for itself, e.g. when the error
+                    // handler emits a metric
+                    if thread::current().id() == worker.id() {
+                        self.drop_item();
+                    } else {
+                        self.push_blocking(rejected, worker);
+                    }
+                }
+            }
+        }
+        if self.queue.len() >= self.wake_threshold
+            && self.parked.load(Ordering::SeqCst)
+            && self.parked.swap(false, Ordering::SeqCst)
+        {
+            worker.unpark();
+        }
+    }
+
+    /// Parks the worker until `timeout`, unless enough items were queued in the meantime
+    fn park(&self, timeout: Duration) {
+        self.parked.store(true, Ordering::SeqCst);
+        // Checked after setting the flag, so that an item pushed before is never left waiting
+        if self.queue.len() < self.wake_threshold {
+            thread::park_timeout(timeout);
+        }
+        self.parked.store(false, Ordering::SeqCst);
+    }
+
+    /// Waits for the worker to make room in the queue. The queue is checked again while holding
+    /// the lock, so that the notification of a drain happening in between can't be missed.
+    fn push_blocking(&self, mut item: Item, worker: &Thread) {
+        let mut blocked = self.blocked.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        loop {
+            match self.queue.push(item) {
+                Ok(()) => return,
+                Err(rejected) => item = rejected,
+            }
+            if self.closed.load(Ordering::Acquire) {
+                self.drop_item();
+                return;
+            }
+            worker.unpark();
+            blocked = match self.room.wait_timeout(blocked, BLOCKED_TIMEOUT) {
+                Ok((blocked, _)) => blocked,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+    }
+
+    /// Wakes up the callers blocked by a full queue
+    fn notify_room(&self) {
+        if self.overflow_policy == OverflowPolicy::Block {
+            let _blocked = self.blocked.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            self.room.notify_all();
+        }
+    }
+
+    fn drop_item(&self) {
+        self.telemetry.dropped_by_queue();
+    }
+}
+
+impl Worker {
+    pub(crate) fn new(
+        formatter: Arc<Formatter>,
+        output: Output,
+        options: WorkerOptions,
+        telemetry: Arc<Telemetry>,
+    ) -> Result<Self, Error> {
+        let wake_threshold = match output {
+            Output::Direct(_) => 1,
+            Output::Batched(_) => options.queue_capacity / 2,
+        };
+        let shared = Arc::new(Shared::new(options, wake_threshold, telemetry));
+        let (control, receiver) = mpsc::channel();
+        let worker_shared = shared.clone();
+        let handle = thread::Builder::new()
+            .name("prima-datadog-worker".to_string())
+            .spawn(move || run(&worker_shared, &formatter, output, receiver))
+            .map_err(DogstatsdError::from)?;
+        Ok(Self {
+            shared,
+            thread: handle.thread().clone(),
+            control: Mutex::new(Some(control)),
+            handle: Mutex::new(Some(handle)),
+        })
+    }
+
+    /// Enqueues an item, applying the overflow policy if the queue is full
+    pub(crate) fn push(&self, item: Item) {
+        self.shared.push(item, &self.thread);
+    }
+
+    /// Whether the worker has been shut down
+    pub(crate) fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::Acquire)
+    }
+
+    /// Waits for the worker to send everything enqueued so far. Called from the worker thread
+    /// itself, e.g. by the error handler, it only asks the worker to send everything once the
+    /// current item is written.
+    pub(crate) fn flush(&self) -> Result<(), Error> {
+        if self.is_worker_thread() {
+            self.shared.flush_requested.store(true, Ordering::Release);
+            return Ok(());
+        }
+        let (ack, done) = mpsc::channel();
+        let control = self.control().clone();
+        if let Some(control) = control {
+            if control.send(Control::Flush(ack)).is_ok() {
+                self.thread.unpark();
+                // The worker only drops the ack without answering if it panicked
+                let _ = done.recv();
+            }
+        }
+        Ok(())
+    }
+
+    /// Stops the worker once it has sent everything enqueued so far, waiting at most `timeout`.
+    /// Called from the worker thread itself, it doesn't wait.
+    pub(crate) fn shutdown(&self, timeout: Duration) -> Result<(), Error> {
+        let (ack, done) = mpsc::channel();
+        let sent = match self.control().take() {
+            Some(control) => control.send(Control::Shutdown(ack)).is_ok(),
+            None => false,
+        };
+        self.thread.unpark();
+        if self.is_worker_thread() {
+            return Ok(());
+        }
+        if sent && done.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+            return Err(Error::ShutdownTimeout);
+        }
+        if let Some(handle) = self
+            .handle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+        {
+            let _ = handle.join();
+        }
+        Ok(())
+    }
+
+    fn is_worker_thread(&self) -> bool {
+        thread::current().id() == self.thread.id()
+    }
+
+    fn control(&self) -> MutexGuard<'_, Option<Sender<Control>>> {
+        self.control.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // Dropping the control channel stops the worker once it has sent the pending items
+        self.control().take();
+        self.thread.unpark();
+    }
+}
+
+fn run(shared: &Shared, formatter: &Formatter, output: Output, control: Receiver<Control>) {
+    let mut writer = Writer::new(formatter, output);
+    loop {
+        drain(shared, &mut writer);
+        if shared.flush_requested.swap(false, Ordering::AcqRel) {
+            writer.send();
+        }
+        match control.try_recv() {
+            Ok(Control::Flush(ack)) => {
+                flush(shared, &mut writer, ack);
+                continue;
+            }
+            Ok(Control::Shutdown(ack)) => {
+                shared.closed.store(true, Ordering::Release);
+                flush(shared, &mut writer, ack);
+                return;
+            }
+            Err(TryRecvError::Disconnected) => {
+                shared.closed.store(true, Ordering::Release);
+                drain(shared, &mut writer);
+                writer.send();
+                return;
+            }
+            Err(TryRecvError::Empty) => {}
+        }
+
+        let now = Instant::now();
+        match writer.output.deadline() {
+            Some(deadline) if deadline <= now => writer.send(),
+            Some(deadline) => shared.park(deadline - now),
+            None => shared.park(IDLE_TIMEOUT),
+        }
+    }
+}
+
+/// Sends everything enqueued so far, including the items pushed since the last drain, right
+/// before the flush was requested
+fn flush(shared: &Shared, writer: &mut Writer<'_>, ack: Sender<()>) {
+    drain(shared, writer);
+    writer.send();
+    let _ = ack.send(());
+}
+
+fn drain(shared: &Shared, writer: &mut Writer<'_>) {
+    while let Some(item) = shared.queue.pop() {
+        writer.write(item);
+    }
+    shared.notify_room();
+}
+
+/// Serializes the items to the output.
+///
+/// When batching, the consecutive histogram and distribution values with the same metric, tags
+/// and rate are packed in a single line, `metric:1:2:3|d`, which is only written once a value of
+/// another context arrives or the batch is sent.
+struct Writer<'a> {
+    formatter: &'a Formatter,
+    output: Output,
+    packed: Option<Packed>,
+}
+
+/// The values packed in the pending line of a [Writer]
+struct Packed {
+    metric: String,
+    values: String,
+    metric_type: MetricType,
+    rate: f64,
+    tags: Vec<String>,
+}
+
+impl Packed {
+    /// Packs the value if it has the same context and there is room left in the line
+    fn pack(&mut self, metric: &str, value: &str, metric_type: MetricType, rate: f64, tags: &[String]) -> bool {
+        self.metric == metric
+            && self.metric_type == metric_type
+            && self.rate == rate
+            && self.tags == tags
+            && format::pack(&mut self.values, value)
+    }
+}
+
+impl<'a> Writer<'a> {
+    fn new(formatter: &'a Formatter, output: Output) -> Self {
+        Self {
+            formatter,
+            output,
+            packed: None,
+        }
+    }
+
+    fn write(&mut self, item: Item) {
+        match item {
+            Item::Metric {
+                metric,
+                value,
+                metric_type,
+                rate,
+                tags,
+                ..
+            } if metric_type.is_packable() && matches!(self.output, Output::Batched(_)) => {
+                if let Some(packed) = &mut self.packed {
+                    if packed.pack(&metric, &value, metric_type, rate, &tags) {
+                        return;
+                    }
+                }
+                self.write_packed();
+                self.packed = Some(Packed {
+                    metric,
+                    values: value,
+                    metric_type,
+                    rate,
+                    tags,
+                });
+            }
+            Item::Metric {
+                metric,
+                value,
+                metric_type,
+                rate,
+                tags,
+                timestamp,
+            } => {
+                self.write_packed();
+                self.output.push(
+                    &self
+                        .formatter
+                        .metric(&metric, &value, metric_type, rate, &tags, timestamp),
+                    &metric,
+                    metric_type,
+                );
+            }
+            Item::Datagram {
+                payload,
+                metric,
+                metric_type,
+            } => {
+                self.write_packed();
+                self.output.push(&payload, &metric, metric_type);
+            }
+        }
+    }
+
+    fn write_packed(&mut self) {
+        if let Some(packed) = self.packed.take() {
+            self.output.push(
+                &self.formatter.metric(
+                    &packed.metric,
+                    &packed.values,
+                    packed.metric_type,
+                    packed.rate,
+                    &packed.tags,
+                    None,
+                ),
+                &packed.metric,
+                packed.metric_type,
+            );
+        }
+    }
+
+    fn send(&mut self) {
+        self.write_packed();
+        self.output.send();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shared(overflow_policy: OverflowPolicy) -> Shared {
+        Shared::new(
+            WorkerOptions {
+                queue_capacity: 2,
+                overflow_policy,
+            },
+            1,
+            Arc::new(Telemetry::default()),
+        )
+    }
+
+    fn item(byte: u8) -> Item {
+        Item::Datagram {
+            payload: vec![byte],
+            metric: String::new(),
+            metric_type: MetricType::Count,
+        }
+    }
+
+    /// Pushes from a thread other than the worker
+    fn push(shared: &Shared, byte: u8) {
+        let worker = thread::spawn(|| {}).thread().clone();
+        shared.push(item(byte), &worker);
+    }
+
+    fn queued(shared: &Shared) -> Vec<u8> {
+        std::iter::from_fn(|| shared.queue.pop())
+            .map(|item| match item {
+                Item::Datagram { payload, .. } => payload[0],
+                Item::Metric { .. } => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        let shared = shared(OverflowPolicy::DropNewest);
+        (1..=3).for_each(|byte| push(&shared, byte));
+
+        assert_eq!(queued(&shared), vec![1, 2]);
+        assert_eq!(shared.telemetry.snapshot().dropped_items, 1);
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let shared = shared(OverflowPolicy::DropOldest);
+        (1..=3).for_each(|byte| push(&shared, byte));
+
+        assert_eq!(queued(&shared), vec![2, 3]);
+        assert_eq!(shared.telemetry.snapshot().dropped_items, 1);
+    }
+
+    #[test]
+    fn test_block() {
+        let shared = Arc::new(shared(OverflowPolicy::Block));
+        (1..=2).for_each(|byte| push(&shared, byte));
+
+        let producer = {
+            let shared = shared.clone();
+            thread::spawn(move || push(&shared, 3))
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!producer.is_finished());
+        assert!(shared.queue.pop().is_some());
+        producer.join().unwrap();
+
+        assert_eq!(queued(&shared), vec![2, 3]);
+        assert_eq!(shared.telemetry.snapshot().dropped_items, 0);
+    }
+
+    #[test]
+    fn test_block_drops_the_newest_on_the_worker_thread() {
+        let shared = shared(OverflowPolicy::Block);
+        (1..=3).for_each(|byte| shared.push(item(byte), &thread::current()));
+
+        assert_eq!(queued(&shared), vec![1, 2]);
+        assert_eq!(shared.telemetry.snapshot().dropped_items, 1);
+    }
+
+    #[test]
+    fn test_push_only_wakes_the_parked_worker_up() {
+        let shared = shared(OverflowPolicy::DropNewest);
+        let worker = thread::current();
+        push(&shared, 1);
+        assert!(!shared.parked.load(Ordering::SeqCst));
+
+        shared.parked.store(true, Ordering::SeqCst);
+        shared.push(item(2), &worker);
+        assert!(!shared.parked.load(Ordering::SeqCst));
+        // The worker was unparked, so parking returns right away
+        shared.queue.pop();
+        shared.queue.pop();
+        let start = Instant::now();
+        shared.park(Duration::from_secs(10));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_block_until_closed() {
+        let shared = Arc::new(shared(OverflowPolicy::Block));
+        (1..=2).for_each(|byte| push(&shared, byte));
+
+        let producer = {
+            let shared = shared.clone();
+            thread::spawn(move || push(&shared, 3))
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!producer.is_finished());
+        shared.closed.store(true, Ordering::Release);
+        shared.notify_room();
+        producer.join().unwrap();
+
+        assert_eq!(queued(&shared), vec![1, 2]);
+        assert_eq!(shared.telemetry.snapshot().dropped_items, 1);
+    }
+
+    #[test]
+    fn test_flush_sends_the_items_pushed_after_the_last_drain() {
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_nonblocking(true).unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        let configuration = crate::configuration::Configuration::new(&address, "").without_telemetry();
+        let shared = shared(OverflowPolicy::DropNewest);
+        let transport = Transport::new(&configuration, shared.telemetry.clone()).unwrap();
+        let formatter = Formatter::new("", &[]);
+        let mut writer = Writer::new(&formatter, Output::Direct(transport));
+        let (ack, done) = mpsc::channel();
+
+        drain(&shared, &mut writer);
+        push(&shared, 1);
+        flush(&shared, &mut writer, ack);
+
+        let mut buf = [0; 16];
+        assert_eq!(socket.recv(&mut buf).ok(), Some(1));
+        assert_eq!(buf[0], 1);
+        assert!(done.try_recv().is_ok());
+    }
+
+    #[test]
+    fn test_closed() {
+        let shared = shared(OverflowPolicy::Block);
+        shared.closed.store(true, Ordering::Release);
+        push(&shared, 1);
+
+        assert!(queued(&shared).is_empty());
+        assert_eq!(shared.telemetry.snapshot().dropped_items, 1);
+    }
+}
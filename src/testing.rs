@@ -0,0 +1,258 @@
+//! Utilities to assert on the metrics emitted by an application.
+//!
+//! This module is only available with the `testing` feature enabled, and is meant to be used in
+//! `dev-dependencies`:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! prima_datadog = { version = "*", features = ["testing"] }
+//! ```
+//!
+//! The [RecordingClient] captures every metric, event and service check in memory instead of
+//! sending them to the agent. It can be installed as the global instance with [install] so that
+//! the macros and the static [Datadog] functions used by the application code are recorded.
+//!
+//! ```
+//! use prima_datadog::{incr, testing::{self, MetricKind}};
+//!
+//! let recorder = testing::install().unwrap();
+//! recorder.clear();
+//!
+//! incr!("orders"; "country" => "it");
+//!
+//! let records = recorder.records_named("orders");
+//! assert_eq!(records.len(), 1);
+//! assert_eq!(records[0].kind, MetricKind::Incr);
+//! assert!(records[0].has_tag("country:it"));
+//! ```
+//!
+//! Since the global instance is shared by the whole test binary, tests asserting on the global
+//! recorder should not run concurrently (e.g. using `serial_test`), or should filter the records
+//! by metric name.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use once_cell::sync::OnceCell;
+
+use crate::{
+    error::Error, Datadog, DynDogstatsdClient, EventOptions, ServiceCheckOptions, ServiceStatus,
+    TagTrackerConfiguration,
+};
+
+static RECORDER: OnceCell<RecordingClient> = OnceCell::new();
+
+/// Installs a [RecordingClient] as the global instance, and returns a handle to it.
+///
+/// Calling this function more than once returns a handle to the same recorder, so every test in
+/// a binary can call it. It fails with [Error::OnceCellAlreadyInitialized] if the global instance
+/// was already initialized with another client.
+pub fn install() -> Result<RecordingClient, Error> {
+    install_with_tracker(TagTrackerConfiguration::new())
+}
+
+/// Same as [install], using the given tag tracker configuration for the global instance.
+///
+/// The tracker configuration is ignored if the recorder was already installed.
+pub fn install_with_tracker(tracker_config: TagTrackerConfiguration) -> Result<RecordingClient, Error> {
+    RECORDER
+        .get_or_try_init(|| {
+            let recorder = RecordingClient::new();
+            Datadog::init_with_client(recorder.clone(), tracker_config)?;
+            Ok(recorder)
+        })
+        .cloned()
+}
+
+/// The kind of a [Record], mirroring the [DynDogstatsdClient] functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Incr,
+    Decr,
+    Count,
+    /// Emitted by `timing`, `timing_value`, and by `time`/`async_time` with the measured
+    /// duration
+    Timing,
+    Gauge,
+    Histogram,
+    Distribution,
+    Set,
+    ServiceCheck,
+    Event,
+}
+
+/// A single metric, event or service check captured by a [RecordingClient].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub kind: MetricKind,
+    /// The metric name, or the title for events
+    pub name: String,
+    /// The value as it would be sent to the agent: `1`/`-1` for incr/decr, the milliseconds for
+    /// timings, the numeric status for service checks and the text for events
+    pub value: String,
+    pub tags: Vec<String>,
+    /// The rate of the metrics emitted with a sample rate. The recorder doesn't sample, so every
+    /// call is recorded.
+    pub sample_rate: Option<f64>,
+    /// The timestamp, in seconds since the unix epoch, of the counts and gauges emitted with an
+    /// explicit timestamp
+    pub timestamp: Option<u64>,
+}
+
+impl Record {
+    /// Returns true if the record has been emitted with the given `key:value` tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+/// A [DynDogstatsdClient] which keeps every emitted metric in memory.
+///
+/// Clones share the same storage, so a clone can be handed to the [Datadog] instance while
+/// keeping another one to inspect the records.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingClient {
+    records: Arc<Mutex<Vec<Record>>>,
+}
+
+impl RecordingClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of all the records captured so far, in emission order.
+    pub fn records(&self) -> Vec<Record> {
+        self.lock().clone()
+    }
+
+    /// Returns the records captured so far with the given metric name (or event title).
+    pub fn records_named(&self, name: &str) -> Vec<Record> {
+        self.lock()
+            .iter()
+            .filter(|record| record.name == name)
+            .cloned()
+            .collect()
+    }
+
+    /// Removes and returns all the records captured so far.
+    pub fn take(&self) -> Vec<Record> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Removes all the records captured so far.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Record>> {
+        // A panicking test must not poison the records for every other test in the binary
+        self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn record(&self, kind: MetricKind, name: &str, value: String, tags: &[&str]) {
+        self.record_with_rate(kind, name, value, None, tags);
+    }
+
+    fn record_with_rate(&self, kind: MetricKind, name: &str, value: String, sample_rate: Option<f64>, tags: &[&str]) {
+        self.lock().push(Record {
+            kind,
+            name: name.to_string(),
+            value,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            sample_rate,
+            timestamp: None,
+        });
+    }
+
+    fn record_at(&self, kind: MetricKind, name: &str, value: String, timestamp: u64, tags: &[&str]) {
+        self.lock().push(Record {
+            kind,
+            name: name.to_string(),
+            value,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            sample_rate: None,
+            timestamp: Some(timestamp),
+        });
+    }
+}
+
+impl DynDogstatsdClient for RecordingClient {
+    fn incr(&self, metric: &str, tags: &[&str]) {
+        self.record(MetricKind::Incr, metric, "1".to_string(), tags);
+    }
+
+    fn decr(&self, metric: &str, tags: &[&str]) {
+        self.record(MetricKind::Decr, metric, "-1".to_string(), tags);
+    }
+
+    fn count(&self, metric: &str, count: i64, tags: &[&str]) {
+        self.record(MetricKind::Count, metric, count.to_string(), tags);
+    }
+
+    fn timing(&self, metric: &str, ms: i64, tags: &[&str]) {
+        self.record(MetricKind::Timing, metric, ms.to_string(), tags);
+    }
+
+    fn timing_value(&self, metric: &str, val: &str, tags: &[&str]) {
+        self.record(MetricKind::Timing, metric, val.to_string(), tags);
+    }
+
+    fn gauge(&self, metric: &str, val: &str, tags: &[&str]) {
+        self.record(MetricKind::Gauge, metric, val.to_string(), tags);
+    }
+
+    fn histogram(&self, metric: &str, val: &str, tags: &[&str]) {
+        self.record(MetricKind::Histogram, metric, val.to_string(), tags);
+    }
+
+    fn distribution(&self, metric: &str, val: &str, tags: &[&str]) {
+        self.record(MetricKind::Distribution, metric, val.to_string(), tags);
+    }
+
+    fn set(&self, metric: &str, val: &str, tags: &[&str]) {
+        self.record(MetricKind::Set, metric, val.to_string(), tags);
+    }
+
+    fn incr_with_rate(&self, metric: &str, rate: f64, tags: &[&str]) {
+        self.record_with_rate(MetricKind::Incr, metric, "1".to_string(), Some(rate), tags);
+    }
+
+    fn decr_with_rate(&self, metric: &str, rate: f64, tags: &[&str]) {
+        self.record_with_rate(MetricKind::Decr, metric, "-1".to_string(), Some(rate), tags);
+    }
+
+    fn count_with_rate(&self, metric: &str, count: i64, rate: f64, tags: &[&str]) {
+        self.record_with_rate(MetricKind::Count, metric, count.to_string(), Some(rate), tags);
+    }
+
+    fn timing_with_rate(&self, metric: &str, ms: i64, rate: f64, tags: &[&str]) {
+        self.record_with_rate(MetricKind::Timing, metric, ms.to_string(), Some(rate), tags);
+    }
+
+    fn histogram_with_rate(&self, metric: &str, val: &str, rate: f64, tags: &[&str]) {
+        self.record_with_rate(MetricKind::Histogram, metric, val.to_string(), Some(rate), tags);
+    }
+
+    fn distribution_with_rate(&self, metric: &str, val: &str, rate: f64, tags: &[&str]) {
+        self.record_with_rate(MetricKind::Distribution, metric, val.to_string(), Some(rate), tags);
+    }
+
+    fn count_at(&self, metric: &str, count: i64, timestamp: u64, tags: &[&str]) {
+        self.record_at(MetricKind::Count, metric, count.to_string(), timestamp, tags);
+    }
+
+    fn gauge_at(&self, metric: &str, val: &str, timestamp: u64, tags: &[&str]) {
+        self.record_at(MetricKind::Gauge, metric, val.to_string(), timestamp, tags);
+    }
+
+    fn service_check(&self, metric: &str, val: ServiceStatus, tags: &[&str], _options: Option<ServiceCheckOptions>) {
+        self.record(MetricKind::ServiceCheck, metric, (val as u32).to_string(), tags);
+    }
+
+    fn event(&self, title: &str, text: &str, tags: &[&str]) {
+        self.record(MetricKind::Event, title, text.to_string(), tags);
+    }
+
+    fn event_with_options(&self, title: &str, text: &str, tags: &[&str], _options: Option<EventOptions>) {
+        self.record(MetricKind::Event, title, text.to_string(), tags);
+    }
+}
@@ -40,4 +40,9 @@ pub fn test_macro() {
     count!("test", 10; tag => "value");
     // mixed tags
     count!("test", 1; "literal" => 1, "expression" => tag);
+    // with a timestamp
+    let timestamp = std::time::SystemTime::now();
+    count!("test", 1, at timestamp);
+    count!("test", 1, at timestamp; "tag1" => "value1");
+    count!("test", 1, at timestamp; tag => "value");
 }
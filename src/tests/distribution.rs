@@ -37,6 +37,20 @@ pub fn distribution_with_type_and_tags() {
     );
 }
 
+#[test]
+pub fn distribution_many_skips_invalid_values() {
+    let mut mock = mocks::MockClient::new();
+    mock.expect_distribution()
+        .times(2)
+        .withf(|metric, value, _| metric == "test" && (value == "1.5" || value == "2"))
+        .return_const(());
+    let datadog = Datadog::new(mock, TagTrackerConfiguration::new());
+
+    datadog.do_distribution_many("test", &[1.5, f64::NAN, 2.0], EMPTY_TAGS);
+
+    assert_eq!(datadog.do_stats().invalid_values, 1);
+}
+
 #[test]
 pub fn test_macro() {
     let tag = String::from("tag");
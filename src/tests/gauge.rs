@@ -44,4 +44,33 @@ pub fn test_macro() {
     gauge!("test", "test_value"; "expression" => tag);
     // mixed tags
     gauge!("test", "1"; "literal" => 1, "expression" => tag);
+    // with a timestamp
+    let timestamp = std::time::SystemTime::now();
+    gauge!("test", 1, at timestamp);
+    gauge!("test", 1, at timestamp; "literal" => 1, "expression" => tag);
+}
+
+#[test]
+pub fn gauge_with_number() {
+    let mock = mocks::gauge_mock("test", "1.5", &[]);
+    Datadog::new(mock, TagTrackerConfiguration::new()).do_gauge("test", 1.5, EMPTY_TAGS);
+}
+
+#[test]
+pub fn gauge_with_nan_is_not_sent() {
+    let datadog = Datadog::new(mocks::MockClient::new(), TagTrackerConfiguration::new());
+    datadog.do_gauge("test", f64::NAN, EMPTY_TAGS);
+    datadog.do_gauge("test", f32::INFINITY, EMPTY_TAGS);
+
+    assert_eq!(datadog.do_stats().invalid_values, 2);
+}
+
+#[test]
+pub fn gauge_in_the_future_is_not_sent() {
+    let datadog = Datadog::new(mocks::MockClient::new(), TagTrackerConfiguration::new());
+    let tomorrow = std::time::SystemTime::now() + std::time::Duration::from_secs(24 * 3600);
+    datadog.do_gauge_at("test", 1, tomorrow, EMPTY_TAGS);
+    datadog.do_count_at("test", 1, tomorrow, EMPTY_TAGS);
+
+    assert_eq!(datadog.do_stats().invalid_values, 2);
 }
@@ -49,3 +49,13 @@ pub fn test_macro() {
     // mixed tags
     histogram!("test", "test_value"; "literal" => 1, "expression" => tag);
 }
+
+#[test]
+pub fn histogram_with_duration() {
+    let mock = mocks::histogram_mock("test", "20", &[]);
+    Datadog::new(mock, TagTrackerConfiguration::new()).do_histogram(
+        "test",
+        std::time::Duration::from_millis(20),
+        EMPTY_TAGS,
+    );
+}
@@ -0,0 +1,63 @@
+use crate::tests::mocks::{self, expect_event, expect_incr, MockClient};
+use crate::Datadog;
+use crate::TagTrackerConfiguration;
+use crate::EMPTY_TAGS;
+
+#[test]
+pub fn namespace_is_prepended_to_metrics() {
+    let mock = mocks::incr_mock("billing.test", &[]);
+    Datadog::new(mock, TagTrackerConfiguration::new())
+        .with_namespace("billing")
+        .do_incr("test", EMPTY_TAGS);
+}
+
+#[test]
+pub fn namespace_is_not_prepended_to_events() {
+    let mock = mocks::event_mock("title", "text", &[]);
+    Datadog::new(mock, TagTrackerConfiguration::new())
+        .with_namespace("billing")
+        .do_event("title", "text", EMPTY_TAGS);
+}
+
+#[test]
+pub fn default_tags_are_merged() {
+    let mock = expect_incr(
+        MockClient::new(),
+        "test",
+        vec!["added:tag".to_string(), "component:pricing".to_string()],
+    );
+    let dd = Datadog::new(mock, TagTrackerConfiguration::new()).with_tag("component", &"pricing");
+
+    assert_eq!(dd.default_tags(), ["component:pricing"]);
+    dd.do_incr("test", ["added:tag"]);
+}
+
+#[test]
+pub fn default_tags_are_added_to_events() {
+    let mock = expect_event(
+        MockClient::new(),
+        "title",
+        "text",
+        vec!["component:pricing".to_string()],
+    );
+    Datadog::new(mock, TagTrackerConfiguration::new())
+        .with_tag("component", &"pricing")
+        .do_event("title", "text", EMPTY_TAGS);
+}
+
+#[test]
+pub fn clones_share_the_tag_tracker() {
+    let threshold = 2;
+    let mut mock = MockClient::new();
+    mock = expect_incr(mock, "test", vec!["0".to_string()]);
+    mock = expect_event(mock, "title", "text", vec![format!("test:{}", threshold)]);
+    mock = expect_incr(mock, "test", vec!["1".to_string()]);
+    let tracker_config = TagTrackerConfiguration::new()
+        .with_threshold(threshold)
+        .with_event("title".to_string(), "text".to_string());
+
+    let dd = Datadog::new(mock, tracker_config);
+    let clone = dd.clone();
+    dd.do_incr("test", ["0"]);
+    clone.do_incr("test", ["1"]);
+}
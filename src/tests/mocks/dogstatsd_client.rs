@@ -1,4 +1,4 @@
-use std::future::Future;
+use std::{future::Future, pin::Pin};
 
 use async_trait::async_trait;
 
@@ -21,140 +21,68 @@ pub(super) trait MockDogstatsdClient {
     fn event_with_options(&self, title: &str, text: &str, tags: Vec<String>, options: Option<EventOptions>);
 }
 
+fn owned(tags: &[&str]) -> Vec<String> {
+    tags.iter().map(|s| s.to_string()).collect()
+}
+
 #[async_trait]
-impl<C: MockDogstatsdClient + Sync> DogstatsdClient for C {
-    fn incr<S: AsRef<str>>(&self, metric: &str, tags: impl TagsProvider<S>) {
-        MockDogstatsdClient::incr(
-            self,
-            metric,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-        )
+impl DynDogstatsdClient for MockClient {
+    fn incr(&self, metric: &str, tags: &[&str]) {
+        MockDogstatsdClient::incr(self, metric, owned(tags))
     }
 
-    fn decr<S: AsRef<str>>(&self, metric: &str, tags: impl TagsProvider<S>) {
-        MockDogstatsdClient::decr(
-            self,
-            metric,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-        )
+    fn decr(&self, metric: &str, tags: &[&str]) {
+        MockDogstatsdClient::decr(self, metric, owned(tags))
     }
 
-    fn count<S: AsRef<str>>(&self, metric: &str, count: i64, tags: impl TagsProvider<S>) {
-        MockDogstatsdClient::count(
-            self,
-            metric,
-            count,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-        )
+    fn count(&self, metric: &str, count: i64, tags: &[&str]) {
+        MockDogstatsdClient::count(self, metric, count, owned(tags))
     }
 
-    fn time<S, F, O>(&self, metric: &str, tags: impl TagsProvider<S>, block: F) -> O
-    where
-        S: AsRef<str>,
-        F: FnOnce() -> O,
-    {
-        MockDogstatsdClient::time(
-            self,
-            metric,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-        );
+    fn time(&self, metric: &str, tags: &[&str], block: Box<dyn FnOnce() + '_>) {
+        MockDogstatsdClient::time(self, metric, owned(tags));
         block()
     }
 
-    async fn async_time<S, F, T, O>(&self, metric: &str, tags: impl TagsProvider<S> + Send + Sync, block: F) -> O
-    where
-        S: AsRef<str> + Sync,
-        F: FnOnce() -> T + Send,
-        T: Future<Output = O> + Send,
-    {
-        MockDogstatsdClient::time(
-            self,
-            metric,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-        );
-        block().await
+    async fn async_time<'a>(
+        &'a self,
+        metric: &'a str,
+        tags: &'a [&'a str],
+        block: Pin<Box<dyn Future<Output = ()> + Send + 'a>>,
+    ) {
+        MockDogstatsdClient::time(self, metric, owned(tags));
+        block.await
     }
 
-    fn timing<S: AsRef<str>>(&self, metric: &str, ms: i64, tags: impl TagsProvider<S>) {
-        MockDogstatsdClient::timing(
-            self,
-            metric,
-            ms,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-        )
+    fn timing(&self, metric: &str, ms: i64, tags: &[&str]) {
+        MockDogstatsdClient::timing(self, metric, ms, owned(tags))
     }
 
-    fn gauge<S: AsRef<str>>(&self, metric: &str, val: &str, tags: impl TagsProvider<S>) {
-        MockDogstatsdClient::gauge(
-            self,
-            metric,
-            val,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-        )
+    fn gauge(&self, metric: &str, val: &str, tags: &[&str]) {
+        MockDogstatsdClient::gauge(self, metric, val, owned(tags))
     }
 
-    fn histogram<S: AsRef<str>>(&self, metric: &str, val: &str, tags: impl TagsProvider<S>) {
-        MockDogstatsdClient::histogram(
-            self,
-            metric,
-            val,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-        )
+    fn histogram(&self, metric: &str, val: &str, tags: &[&str]) {
+        MockDogstatsdClient::histogram(self, metric, val, owned(tags))
     }
 
-    fn distribution<S: AsRef<str>>(&self, metric: &str, val: &str, tags: impl TagsProvider<S>) {
-        MockDogstatsdClient::distribution(
-            self,
-            metric,
-            val,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-        )
+    fn distribution(&self, metric: &str, val: &str, tags: &[&str]) {
+        MockDogstatsdClient::distribution(self, metric, val, owned(tags))
     }
 
-    fn set<S: AsRef<str>>(&self, metric: &str, val: &str, tags: impl TagsProvider<S>) {
-        MockDogstatsdClient::set(
-            self,
-            metric,
-            val,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-        )
+    fn set(&self, metric: &str, val: &str, tags: &[&str]) {
+        MockDogstatsdClient::set(self, metric, val, owned(tags))
     }
 
-    fn service_check<S: AsRef<str>>(
-        &self,
-        metric: &str,
-        val: ServiceStatus,
-        tags: impl TagsProvider<S>,
-        options: Option<ServiceCheckOptions>,
-    ) {
-        MockDogstatsdClient::service_check(
-            self,
-            metric,
-            val,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-            options,
-        )
+    fn service_check(&self, metric: &str, val: ServiceStatus, tags: &[&str], options: Option<ServiceCheckOptions>) {
+        MockDogstatsdClient::service_check(self, metric, val, owned(tags), options)
     }
 
-    fn event<S: AsRef<str>>(&self, title: &str, text: &str, tags: impl TagsProvider<S>) {
-        MockDogstatsdClient::event(
-            self,
-            title,
-            text,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-        )
+    fn event(&self, title: &str, text: &str, tags: &[&str]) {
+        MockDogstatsdClient::event(self, title, text, owned(tags))
     }
 
-    fn event_with_options<S>(&self, title: &str, text: &str, tags: impl TagsProvider<S>, options: Option<EventOptions>)
-    where
-        S: AsRef<str>,
-    {
-        MockDogstatsdClient::event_with_options(
-            self,
-            title,
-            text,
-            tags.as_ref().iter().map(|s| s.as_ref().to_string()).collect(),
-            options,
-        )
+    fn event_with_options(&self, title: &str, text: &str, tags: &[&str], options: Option<EventOptions>) {
+        MockDogstatsdClient::event_with_options(self, title, text, owned(tags), options)
     }
 }
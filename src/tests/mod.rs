@@ -11,9 +11,12 @@ mod event_with_options;
 mod gauge;
 mod histogram;
 mod incr;
-mod mocks;
+mod instance;
+pub(crate) mod mocks;
 mod service_check;
 mod set;
+#[cfg(feature = "testing")]
+mod testing;
 mod time;
 mod timing;
 mod tracker;
@@ -0,0 +1,82 @@
+use crate::testing::{MetricKind, Record, RecordingClient};
+use crate::tests::TestEvent;
+use crate::Datadog;
+use crate::TagTrackerConfiguration;
+use crate::EMPTY_TAGS;
+
+#[test]
+pub fn recording_client_captures_metrics_in_order() {
+    let recorder = RecordingClient::new();
+    let dd = Datadog::new(recorder.clone(), TagTrackerConfiguration::new());
+
+    dd.do_incr("test", EMPTY_TAGS);
+    dd.do_count(TestEvent::Test1, 10, vec!["added:tag".to_string()]);
+    dd.do_gauge("test", "gauge_value", ["env:test"]);
+    dd.do_event("title", "text", EMPTY_TAGS);
+
+    assert_eq!(
+        recorder.records(),
+        vec![
+            Record {
+                kind: MetricKind::Incr,
+                name: "test".to_string(),
+                value: "1".to_string(),
+                tags: vec![],
+                sample_rate: None,
+                timestamp: None,
+            },
+            Record {
+                kind: MetricKind::Count,
+                name: "test1_event".to_string(),
+                value: "10".to_string(),
+                tags: vec!["added:tag".to_string()],
+                sample_rate: None,
+                timestamp: None,
+            },
+            Record {
+                kind: MetricKind::Gauge,
+                name: "test".to_string(),
+                value: "gauge_value".to_string(),
+                tags: vec!["env:test".to_string()],
+                sample_rate: None,
+                timestamp: None,
+            },
+            Record {
+                kind: MetricKind::Event,
+                name: "title".to_string(),
+                value: "text".to_string(),
+                tags: vec![],
+                sample_rate: None,
+                timestamp: None,
+            },
+        ]
+    );
+}
+
+#[test]
+pub fn recording_client_take_empties_the_records() {
+    let recorder = RecordingClient::new();
+    let dd = Datadog::new(recorder.clone(), TagTrackerConfiguration::new());
+
+    dd.do_incr("test", EMPTY_TAGS);
+    dd.do_decr("test", EMPTY_TAGS);
+
+    let records = recorder.take();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1].kind, MetricKind::Decr);
+    assert!(recorder.records().is_empty());
+}
+
+#[test]
+pub fn recording_client_records_time_as_timing() {
+    let recorder = RecordingClient::new();
+    let dd = Datadog::new(recorder.clone(), TagTrackerConfiguration::new());
+
+    let output = dd.do_time("test", ["added:tag"], || "output");
+
+    assert_eq!(output, "output");
+    let records = recorder.records_named("test");
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].kind, MetricKind::Timing);
+    assert!(records[0].has_tag("added:tag"));
+}
@@ -2,6 +2,7 @@ use crate::tests::mocks;
 use crate::tests::TestEvent;
 use crate::time;
 use crate::Datadog;
+use crate::DynDogstatsdClient;
 use crate::TagTrackerConfiguration;
 use crate::EMPTY_TAGS;
 
@@ -41,3 +42,10 @@ pub fn test_macro() {
     // mixed tags
     time!("test", || {}; "literal" => 1, "expression" => tag);
 }
+
+#[test]
+pub fn time_with_boxed_client() {
+    let mock: Box<dyn DynDogstatsdClient> = Box::new(mocks::time_mock("test", &["added:tag"]));
+    let output = Datadog::new(mock, TagTrackerConfiguration::new()).do_time("test", ["added:tag"], || 42);
+    assert_eq!(output, 42);
+}
@@ -41,3 +41,26 @@ pub fn test_macro() {
     // mixed tags
     timing!("test", 1000; "literal" => 1, "expression" => tag);
 }
+
+#[test]
+pub fn timing_duration_in_milliseconds() {
+    let mock = mocks::timing_mock("test", 20, &[]);
+    Datadog::new(mock, TagTrackerConfiguration::new()).do_timing_duration(
+        "test",
+        std::time::Duration::from_micros(20_900),
+        EMPTY_TAGS,
+    );
+}
+
+#[test]
+pub fn timing_duration_as_distribution() {
+    use crate::configuration::{TimingOptions, TimingUnit};
+
+    let mock = mocks::distribution_mock("test", "1250", &["added:tag"]);
+    Datadog::new(mock, TagTrackerConfiguration::new())
+        .with_timing_options(TimingOptions {
+            unit: TimingUnit::Microseconds,
+            as_distribution: true,
+        })
+        .do_timing_duration("test", std::time::Duration::from_micros(1250), ["added:tag"]);
+}
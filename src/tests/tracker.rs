@@ -99,6 +99,25 @@ pub fn check_event_sent_exactly_once() {
     }
 }
 
+#[test]
+pub fn check_tracker_events_are_counted() {
+    let threshold = 3;
+    let mut mock = MockClient::new();
+    mock = expect_event(mock, "title", "text", vec![format!("test:{}", threshold)]);
+    for i in 0..10 {
+        mock = expect_incr(mock, "test", vec![format!("{}", i)]);
+    }
+    let tracking_config = TagTrackerConfiguration::new()
+        .with_threshold(threshold)
+        .with_event("title".to_string(), "text".to_string());
+    let dd = Datadog::new(mock, tracking_config);
+    assert_eq!(dd.do_stats().tracker_events, 0);
+    for i in 0..10 {
+        dd.do_incr("test", vec![format!("{}", i)]);
+    }
+    assert_eq!(dd.do_stats().tracker_events, 1);
+}
+
 #[test]
 pub fn check_algorithm_counts_unique_sets_directly() {
     let threshold = 3;
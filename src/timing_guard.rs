@@ -4,7 +4,8 @@ use crate::{Datadog, TagsProvider};
 
 pub const EXPERIMENTS_METRIC_NAME: &str = "experiments";
 
-/// A guard which emits a timing metric when dropped.
+/// A guard which emits a timing metric when dropped, in the unit of the
+/// [TimingOptions](crate::configuration::TimingOptions) of the global instance.
 pub struct TimingGuard<S, P>
 where
     S: AsRef<str>,
@@ -38,8 +39,8 @@ where
 {
     fn drop(&mut self) {
         let elapsed = self.start.elapsed();
-        match elapsed.as_millis().try_into() {
-            Ok(millis) => Datadog::timing(&self.name, millis, &self.tags),
+        match TryInto::<i64>::try_into(elapsed.as_millis()) {
+            Ok(_) => Datadog::timing_duration(&self.name, elapsed, &self.tags),
             Err(_) => {
                 let mut tags: Vec<_> = self.tags.as_ref().iter().map(|t| t.as_ref()).collect();
                 tags.push("overflowed");
@@ -1,7 +1,10 @@
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     iter::FromIterator,
-    sync::Mutex,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use crate::{DogstatsdClient, TagsProvider};
@@ -28,10 +31,14 @@ pub(crate) struct Tracker {
     ///
     /// This will be `None` if the user does not want to track cardinality.
     state: Option<Mutex<TrackerState>>,
+
+    /// The number of actions taken so far, shared with the telemetry of the
+    /// [StatsdClient](crate::statsd::StatsdClient) created from the same configuration
+    actions_fired: Arc<AtomicU64>,
 }
 
 impl Tracker {
-    fn new(cardinality_threshold: usize, actions: Vec<ThresholdAction>) -> Self {
+    fn new(cardinality_threshold: usize, actions: Vec<ThresholdAction>, actions_fired: Arc<AtomicU64>) -> Self {
         Tracker {
             cardinality_threshold,
             state: if !actions.is_empty() && cardinality_threshold != 0 {
@@ -43,9 +50,14 @@ impl Tracker {
             } else {
                 None
             },
+            actions_fired,
         }
     }
 
+    pub(crate) fn actions_fired(&self) -> u64 {
+        self.actions_fired.load(Ordering::Relaxed)
+    }
+
     pub(crate) fn track<S, T>(&self, dd: &impl DogstatsdClient, metric: &str, tags: T) -> T
     where
         S: AsRef<str>,
@@ -68,6 +80,7 @@ impl Tracker {
                     cardinality_count += 1;
                     if cardinality_count >= self.cardinality_threshold {
                         drop(lock);
+                        self.actions_fired.fetch_add(actions.len() as u64, Ordering::Relaxed);
                         Self::do_actions(dd, seen, actions, metric, tag_slice);
                         return tags;
                     }
@@ -125,7 +138,7 @@ impl Tracker {
             .collect();
         for action in actions {
             match action {
-                ThresholdAction::Event { title, text } => dd.event(&title, &text, &event_tags),
+                ThresholdAction::Event { title, text } => DogstatsdClient::event(dd, &title, &text, &event_tags),
                 ThresholdAction::Custom(mut action) => {
                     action(metric, &tags, &seen);
                 }
@@ -167,6 +180,7 @@ type ThresholdCustomAction = Box<dyn FnMut(&str, &[&str], &HashMap<String, Vec<H
 pub struct TagTrackerConfiguration {
     count_threshold: usize,
     actions: Vec<ThresholdAction>,
+    actions_fired: Arc<AtomicU64>,
 }
 
 impl Default for TagTrackerConfiguration {
@@ -174,6 +188,7 @@ impl Default for TagTrackerConfiguration {
         Self {
             count_threshold: DEFAULT_TAG_THRESHOLD,
             actions: Vec::new(),
+            actions_fired: Arc::default(),
         }
     }
 }
@@ -183,6 +198,7 @@ impl TagTrackerConfiguration {
         Self {
             count_threshold: DEFAULT_TAG_THRESHOLD,
             actions: Vec::new(),
+            actions_fired: Arc::default(),
         }
     }
 
@@ -240,7 +256,12 @@ impl TagTrackerConfiguration {
         self
     }
 
+    /// The number of actions taken by the tracker built from this configuration
+    pub(crate) fn actions_fired(&self) -> Arc<AtomicU64> {
+        self.actions_fired.clone()
+    }
+
     pub(crate) fn build(self) -> Tracker {
-        Tracker::new(self.count_threshold, self.actions)
+        Tracker::new(self.count_threshold, self.actions, self.actions_fired)
     }
 }
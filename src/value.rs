@@ -0,0 +1,199 @@
+//! Typed values of gauges, histograms, distributions and sets
+
+use std::{
+    borrow::Cow,
+    fmt::{self, Write},
+    time::Duration,
+};
+
+/// A value which can be reported as a gauge, a histogram, a distribution or a set.
+///
+/// It is implemented for strings, which are sent as they are, for the integer and floating point
+/// types, which are formatted without allocating, and for [Duration], which is sent in
+/// milliseconds.
+///
+/// NaN and infinite floats can't be sent to the agent: the metric is not sent and the value is
+/// counted in [ClientStats::invalid_values](crate::ClientStats::invalid_values).
+///
+/// ```
+/// use std::time::Duration;
+/// use prima_datadog::{distribution, gauge, histogram};
+///
+/// gauge!("queue.size", 42);
+/// histogram!("request.size", 1.5; "endpoint" => "quotes");
+/// distribution!("request.duration", Duration::from_millis(20));
+/// ```
+pub trait MetricValue {
+    /// Returns the value as it is sent to the agent, using `buffer` if it needs to be formatted,
+    /// or `None` if it can't be sent
+    fn format_value<'a>(&'a self, buffer: &'a mut ValueBuffer) -> Option<&'a str>;
+}
+
+/// A stack buffer for the values formatted by [MetricValue::format_value]
+#[derive(Debug)]
+pub struct ValueBuffer {
+    buf: [u8; 64],
+    len: usize,
+}
+
+impl ValueBuffer {
+    pub fn new() -> Self {
+        Self { buf: [0; 64], len: 0 }
+    }
+
+    /// Formats `value` in the buffer, returning `None` if it doesn't fit
+    pub fn format(&mut self, value: fmt::Arguments<'_>) -> Option<&str> {
+        self.len = 0;
+        self.write_fmt(value).ok()?;
+        std::str::from_utf8(&self.buf[..self.len]).ok()
+    }
+}
+
+impl Default for ValueBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for ValueBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl MetricValue for str {
+    fn format_value<'a>(&'a self, _: &'a mut ValueBuffer) -> Option<&'a str> {
+        Some(self)
+    }
+}
+
+impl MetricValue for String {
+    fn format_value<'a>(&'a self, _: &'a mut ValueBuffer) -> Option<&'a str> {
+        Some(self)
+    }
+}
+
+impl MetricValue for Cow<'_, str> {
+    fn format_value<'a>(&'a self, _: &'a mut ValueBuffer) -> Option<&'a str> {
+        Some(self)
+    }
+}
+
+impl<T: MetricValue + ?Sized> MetricValue for &T {
+    fn format_value<'a>(&'a self, buffer: &'a mut ValueBuffer) -> Option<&'a str> {
+        (**self).format_value(buffer)
+    }
+}
+
+macro_rules! impl_integer_value {
+    ($($ty:ty),*) => {
+        $(
+            impl MetricValue for $ty {
+                fn format_value<'a>(&'a self, buffer: &'a mut ValueBuffer) -> Option<&'a str> {
+                    buffer.format(format_args!("{}", self))
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_value!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_float_value {
+    ($($ty:ty),*) => {
+        $(
+            impl MetricValue for $ty {
+                fn format_value<'a>(&'a self, buffer: &'a mut ValueBuffer) -> Option<&'a str> {
+                    if !self.is_finite() {
+                        return None;
+                    }
+                    // Display never uses the exponent notation, so very large or very small
+                    // values would not fit the buffer
+                    let abs = self.abs();
+                    if abs == 0.0 || (1e-5..1e16).contains(&abs) {
+                        buffer.format(format_args!("{}", self))
+                    } else {
+                        buffer.format(format_args!("{:e}", self))
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_float_value!(f32, f64);
+
+impl MetricValue for Duration {
+    fn format_value<'a>(&'a self, buffer: &'a mut ValueBuffer) -> Option<&'a str> {
+        format_millis(*self, buffer)
+    }
+}
+
+/// Formats the duration in milliseconds with a decimal part, computed from the nanoseconds with
+/// integer math so that e.g. 1.1ms is not sent as `1.1000000000000001`
+pub(crate) fn format_millis(duration: Duration, buffer: &mut ValueBuffer) -> Option<&str> {
+    let nanos = duration.as_nanos();
+    let millis = nanos / 1_000_000;
+    let fraction = nanos % 1_000_000;
+    if fraction == 0 {
+        return buffer.format(format_args!("{}", millis));
+    }
+    buffer
+        .format(format_args!("{}.{:06}", millis, fraction))
+        .map(|formatted| formatted.trim_end_matches('0'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(value: impl MetricValue) -> Option<String> {
+        value.format_value(&mut ValueBuffer::new()).map(str::to_string)
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(format("value").as_deref(), Some("value"));
+        assert_eq!(format(String::from("value")).as_deref(), Some("value"));
+        let value = String::from("value");
+        assert_eq!(format(&value).as_deref(), Some("value"));
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(format(42).as_deref(), Some("42"));
+        assert_eq!(format(-42i64).as_deref(), Some("-42"));
+        assert_eq!(
+            format(u128::MAX).as_deref(),
+            Some("340282366920938463463374607431768211455")
+        );
+        assert_eq!(format(1.5).as_deref(), Some("1.5"));
+        assert_eq!(format(2.0f32).as_deref(), Some("2"));
+        assert_eq!(format(0.0).as_deref(), Some("0"));
+        assert_eq!(format(1e300).as_deref(), Some("1e300"));
+        assert_eq!(format(-1.5e-10).as_deref(), Some("-1.5e-10"));
+        assert_eq!(format(f64::MAX).as_deref(), Some("1.7976931348623157e308"));
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(format(Duration::from_millis(20)).as_deref(), Some("20"));
+        assert_eq!(format(Duration::from_micros(1500)).as_deref(), Some("1.5"));
+        assert_eq!(format(Duration::from_micros(1100)).as_deref(), Some("1.1"));
+        assert_eq!(format(Duration::from_nanos(20_000_007)).as_deref(), Some("20.000007"));
+        assert_eq!(format(Duration::from_nanos(450)).as_deref(), Some("0.00045"));
+    }
+
+    #[test]
+    fn test_invalid_floats() {
+        assert_eq!(format(f64::NAN), None);
+        assert_eq!(format(f64::INFINITY), None);
+        assert_eq!(format(f32::NEG_INFINITY), None);
+    }
+}
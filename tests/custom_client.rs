@@ -0,0 +1,84 @@
+use std::sync::{Arc, Mutex};
+
+use prima_datadog::{
+    configuration::Configuration, error::Error, Datadog, DynDogstatsdClient, EventOptions, ServiceCheckOptions,
+    ServiceStatus, TagTrackerConfiguration,
+};
+
+/// A client that only keeps the name of the emitted metrics
+#[derive(Clone, Default)]
+struct NamesClient(Arc<Mutex<Vec<String>>>);
+
+impl NamesClient {
+    fn push(&self, metric: &str) {
+        self.0.lock().unwrap().push(metric.to_string());
+    }
+}
+
+impl DynDogstatsdClient for NamesClient {
+    fn incr(&self, metric: &str, _tags: &[&str]) {
+        self.push(metric)
+    }
+
+    fn decr(&self, metric: &str, _tags: &[&str]) {
+        self.push(metric)
+    }
+
+    fn count(&self, metric: &str, _count: i64, _tags: &[&str]) {
+        self.push(metric)
+    }
+
+    fn timing(&self, metric: &str, _ms: i64, _tags: &[&str]) {
+        self.push(metric)
+    }
+
+    fn gauge(&self, metric: &str, _val: &str, _tags: &[&str]) {
+        self.push(metric)
+    }
+
+    fn histogram(&self, metric: &str, _val: &str, _tags: &[&str]) {
+        self.push(metric)
+    }
+
+    fn distribution(&self, metric: &str, _val: &str, _tags: &[&str]) {
+        self.push(metric)
+    }
+
+    fn set(&self, metric: &str, _val: &str, _tags: &[&str]) {
+        self.push(metric)
+    }
+
+    fn service_check(&self, metric: &str, _val: ServiceStatus, _tags: &[&str], _options: Option<ServiceCheckOptions>) {
+        self.push(metric)
+    }
+
+    fn event(&self, title: &str, _text: &str, _tags: &[&str]) {
+        self.push(title)
+    }
+
+    fn event_with_options(&self, title: &str, _text: &str, _tags: &[&str], _options: Option<EventOptions>) {
+        self.push(title)
+    }
+}
+
+#[test]
+fn macros_dispatch_to_the_boxed_custom_client() {
+    let names = NamesClient::default();
+    // The client can be chosen at runtime
+    let client: Box<dyn DynDogstatsdClient> = Box::new(names.clone());
+    Datadog::init_with_client(client, TagTrackerConfiguration::new()).unwrap();
+
+    prima_datadog::incr!("first"; "some" => "data");
+    prima_datadog::gauge!("second", "10");
+    let output = prima_datadog::time!("third", || 42);
+    prima_datadog::event!("fourth", "text");
+
+    assert_eq!(output, 42);
+    assert_eq!(*names.0.lock().unwrap(), vec!["first", "second", "third", "fourth"]);
+
+    // The global instance can only be initialized once, whatever the client
+    let result = Datadog::init(Configuration::new("127.0.0.1:8125", "namespace"));
+    assert!(matches!(result, Err(Error::OnceCellAlreadyInitialized)));
+    let result = Datadog::init_with_client(NamesClient::default(), TagTrackerConfiguration::new());
+    assert!(matches!(result, Err(Error::OnceCellAlreadyInitialized)));
+}
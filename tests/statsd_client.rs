@@ -0,0 +1,658 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    net::UdpSocket,
+    os::unix::net::{UnixDatagram, UnixListener, UnixStream},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use prima_datadog::{
+    aggregation::AggregatingClient,
+    configuration::{
+        AggregationOptions, BatchingOptions, Configuration, OverflowPolicy, SketchOptions, TimingOptions, TimingUnit,
+        WorkerOptions,
+    },
+    error::Error,
+    incr,
+    statsd::{MetricType, StatsdClient},
+    Datadog, TagTrackerConfiguration,
+};
+
+const BATCHING_OPTIONS: BatchingOptions = BatchingOptions {
+    max_buffer_size: 8000,
+    max_time: Duration::from_secs(3600),
+    max_retry_attempts: 3,
+    initial_retry_delay: 10,
+};
+
+fn bind_receiver() -> (UdpSocket, String) {
+    let socket = UdpSocket::bind("127.0.0.1:0").expect("couldn't open udp socket");
+    socket.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+    let address = format!("127.0.0.1:{}", socket.local_addr().unwrap().port());
+    (socket, address)
+}
+
+fn read_string_from(socket: &UdpSocket) -> Option<String> {
+    let mut buf = [0; 8000];
+    let (length, _) = socket.recv_from(&mut buf).ok()?;
+    Some(String::from_utf8(buf[..length].to_vec()).unwrap())
+}
+
+#[test]
+fn flush_sends_the_pending_batch() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "flush").with_batching_options(BATCHING_OPTIONS);
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    datadog.do_incr("first", ["a:b"]);
+    datadog.do_count("second", 3, prima_datadog::EMPTY_TAGS);
+    assert_eq!(read_string_from(&socket), None);
+
+    datadog.do_flush().unwrap();
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some("flush.first:1|c|#a:b\nflush.second:3|c")
+    );
+}
+
+#[test]
+fn shutdown_guard_flushes_the_global_instance() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "shutdown").with_batching_options(BATCHING_OPTIONS);
+    Datadog::init(configuration).unwrap();
+
+    {
+        let _guard = Datadog::shutdown_guard(Duration::from_secs(1));
+        incr!("before_shutdown");
+    }
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some("shutdown.before_shutdown:1|c")
+    );
+
+    // Metrics emitted after the shutdown are dropped
+    incr!("after_shutdown");
+    Datadog::flush().unwrap();
+    assert_eq!(read_string_from(&socket), None);
+}
+
+#[test]
+fn worker_sends_each_metric_from_the_background_thread() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "worker").with_worker_options(WorkerOptions {
+        queue_capacity: 16,
+        overflow_policy: OverflowPolicy::DropOldest,
+    });
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    datadog.do_incr("first", ["a:b"]);
+    datadog.do_gauge("second", "12", prima_datadog::EMPTY_TAGS);
+    datadog.do_flush().unwrap();
+
+    assert_eq!(read_string_from(&socket).as_deref(), Some("worker.first:1|c|#a:b"));
+    assert_eq!(read_string_from(&socket).as_deref(), Some("worker.second:12|g"));
+    assert_eq!(datadog.do_dropped_items(), 0);
+
+    datadog.do_shutdown(Duration::from_secs(1)).unwrap();
+    datadog.do_incr("after_shutdown", prima_datadog::EMPTY_TAGS);
+    assert_eq!(datadog.do_dropped_items(), 1);
+}
+
+#[test]
+fn telemetry_is_reported_periodically() {
+    let (socket, address) = bind_receiver();
+    let mut configuration = Configuration::new(&address, "telemetry")
+        .with_telemetry_interval(Duration::from_millis(50))
+        .with_tracker_configuration(
+            TagTrackerConfiguration::new()
+                .with_threshold(1)
+                .with_custom_action(|_, _, _| {}),
+        );
+    let client = StatsdClient::new(&configuration).unwrap();
+    let datadog = Datadog::new(client, configuration.take_tracker_config());
+
+    datadog.do_incr("test", prima_datadog::EMPTY_TAGS);
+    assert_eq!(read_string_from(&socket).as_deref(), Some("telemetry.test:1|c"));
+
+    let version = env!("CARGO_PKG_VERSION");
+    let tags = format!("client:rust,client_version:{version},client_transport:udp");
+    assert_eq!(
+        read_string_from(&socket),
+        Some(format!("datadog.dogstatsd.client.metrics:1|c|#{tags}"))
+    );
+    assert_eq!(
+        read_string_from(&socket),
+        Some(format!("datadog.dogstatsd.client.packets_sent:1|c|#{tags}"))
+    );
+    assert_eq!(
+        read_string_from(&socket),
+        Some(format!("datadog.dogstatsd.client.bytes_sent:18|c|#{tags}"))
+    );
+    assert_eq!(
+        read_string_from(&socket),
+        Some(format!("datadog.dogstatsd.client.tracker_events:1|c|#{tags}"))
+    );
+
+    let stats = datadog.do_stats();
+    assert_eq!(stats.metrics, 1);
+    assert_eq!(stats.tracker_events, 1);
+    assert!(stats.packets_sent >= 4);
+    assert_eq!(stats.packets_dropped, 0);
+}
+
+#[test]
+fn send_errors_are_reported_to_the_error_handler() {
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let handler_errors = errors.clone();
+    let configuration = Configuration::new("", "errors")
+        .with_socket_path("/nonexistent/dsd.socket".to_string())
+        .without_telemetry()
+        .with_error_handler(move |_, metric, metric_type| {
+            handler_errors.lock().unwrap().push((metric.to_string(), metric_type));
+        });
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    datadog.do_incr("first", prima_datadog::EMPTY_TAGS);
+    datadog.do_gauge("second", "1", prima_datadog::EMPTY_TAGS);
+
+    // The second error is within the rate limit
+    assert_eq!(*errors.lock().unwrap(), vec![("first".to_string(), MetricType::Count)]);
+    assert_eq!(datadog.do_stats().packets_dropped, 2);
+}
+
+#[test]
+fn the_error_handler_can_flush_and_emit_from_the_worker_thread() {
+    let handler_datadog = Arc::new(Mutex::new(None::<Datadog<StatsdClient>>));
+    let handler = handler_datadog.clone();
+    let configuration = Configuration::new("", "errors")
+        .with_socket_path("/nonexistent/dsd.socket".to_string())
+        .with_worker_options(WorkerOptions {
+            queue_capacity: 1,
+            overflow_policy: OverflowPolicy::Block,
+        })
+        .without_telemetry()
+        .with_error_handler(move |_, _, _| {
+            if let Some(datadog) = handler.lock().unwrap().clone() {
+                // Enough metrics to fill the queue, which only the worker thread drains
+                for _ in 0..3 {
+                    datadog.do_incr("from_handler", prima_datadog::EMPTY_TAGS);
+                }
+                datadog.do_flush().unwrap();
+            }
+        });
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+    *handler_datadog.lock().unwrap() = Some(datadog.clone());
+
+    let (done, flushed) = std::sync::mpsc::channel();
+    let flushing = datadog.clone();
+    std::thread::spawn(move || {
+        flushing.do_incr("first", prima_datadog::EMPTY_TAGS);
+        flushing.do_flush().unwrap();
+        done.send(()).unwrap();
+    });
+
+    assert!(flushed.recv_timeout(Duration::from_secs(5)).is_ok());
+    assert!(datadog.do_stats().dropped_items >= 1);
+    handler_datadog.lock().unwrap().take();
+}
+
+#[test]
+fn oversized_metrics_are_dropped_and_counted() {
+    let (socket, address) = bind_receiver();
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let handler_errors = errors.clone();
+    let configuration = Configuration::new(&address, "oversized")
+        .with_max_payload_size(32)
+        .without_telemetry()
+        .with_error_handler(move |error, metric, _| {
+            handler_errors
+                .lock()
+                .unwrap()
+                .push((metric.to_string(), error.to_string()));
+        });
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    datadog.do_incr("a_metric_name_which_can_never_fit", ["a:b"]);
+    datadog.do_incr("fits", prima_datadog::EMPTY_TAGS);
+
+    assert_eq!(read_string_from(&socket).as_deref(), Some("oversized.fits:1|c"));
+    assert_eq!(datadog.do_stats().oversized_payloads, 1);
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors[0].0, "a_metric_name_which_can_never_fit");
+    assert!(errors[0].1.contains("exceeds the maximum payload size of 32 bytes"));
+}
+
+#[test]
+fn batches_are_split_on_lines_to_fit_the_max_payload_size() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "split")
+        .with_max_payload_size(40)
+        .with_batching_options(BATCHING_OPTIONS);
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    datadog.do_count("first", 1, prima_datadog::EMPTY_TAGS);
+    datadog.do_count("second", 2, prima_datadog::EMPTY_TAGS);
+    datadog.do_count("third", 3, prima_datadog::EMPTY_TAGS);
+    datadog.do_count("a_metric_name_which_can_never_fit", 4, ["a:b"]);
+    datadog.do_flush().unwrap();
+
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some("split.first:1|c\nsplit.second:2|c")
+    );
+    assert_eq!(read_string_from(&socket).as_deref(), Some("split.third:3|c"));
+    assert_eq!(read_string_from(&socket), None);
+    assert_eq!(datadog.do_stats().oversized_payloads, 1);
+}
+
+#[test]
+fn sampled_metrics_are_sent_with_their_rate() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "sampled").with_sample_rate("configured", 0.5);
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    (0..100).for_each(|_| datadog.do_incr_with_rate("never", 0.0, ["a:b"]));
+    assert_eq!(read_string_from(&socket), None);
+
+    // Sending none of 100 metrics sampled at 0.5 is practically impossible
+    (0..100).for_each(|_| datadog.do_timing_with_rate("explicit", 20, 0.5, ["a:b"]));
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some("sampled.explicit:20|ms|@0.5|#a:b")
+    );
+    while read_string_from(&socket).is_some() {}
+
+    (0..100).for_each(|_| datadog.do_incr("configured", prima_datadog::EMPTY_TAGS));
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some("sampled.configured:1|c|@0.5")
+    );
+}
+
+#[test]
+fn timings_are_sent_in_the_configured_unit() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "timings");
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    )
+    .with_timing_options(TimingOptions {
+        unit: TimingUnit::FractionalMilliseconds,
+        as_distribution: false,
+    });
+
+    datadog.do_timing_duration("lookup", Duration::from_micros(250), prima_datadog::EMPTY_TAGS);
+    assert_eq!(read_string_from(&socket).as_deref(), Some("timings.lookup:0.25|ms"));
+
+    datadog.do_time("block", prima_datadog::EMPTY_TAGS, || {
+        std::thread::sleep(Duration::from_millis(2))
+    });
+    let datagram = read_string_from(&socket).unwrap();
+    let value = datagram
+        .strip_prefix("timings.block:")
+        .and_then(|datagram| datagram.strip_suffix("|ms"))
+        .unwrap();
+    assert!(value.parse::<f64>().unwrap() >= 2.0, "{}", datagram);
+}
+
+#[test]
+fn timers_in_microseconds_and_nanoseconds_have_the_unit_in_their_name() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "timings").without_telemetry();
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+    let duration = Duration::from_micros(1_250);
+
+    for (unit, datagram) in [
+        (TimingUnit::Microseconds, "timings.lookup.us:1250|ms"),
+        (TimingUnit::Nanoseconds, "timings.lookup.ns:1250000|ms"),
+    ] {
+        let timer = datadog.clone().with_timing_options(TimingOptions {
+            unit,
+            as_distribution: false,
+        });
+        timer.do_timing_duration("lookup", duration, prima_datadog::EMPTY_TAGS);
+        assert_eq!(read_string_from(&socket).as_deref(), Some(datagram));
+    }
+
+    let distribution = datadog.with_timing_options(TimingOptions {
+        unit: TimingUnit::Microseconds,
+        as_distribution: true,
+    });
+    distribution.do_timing_duration("lookup", duration, prima_datadog::EMPTY_TAGS);
+    assert_eq!(read_string_from(&socket).as_deref(), Some("timings.lookup:1250|d"));
+}
+
+#[test]
+fn many_values_are_packed_in_a_single_line() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "packed");
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    datadog.do_distribution_many("latency", &[1.5, 2.0, 3.25], ["a:b"]);
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some("packed.latency:1.5:2:3.25|d|#a:b")
+    );
+
+    // The values are split in several lines rather than exceeding the size of a packet
+    let values: Vec<u64> = (0..1000).collect();
+    datadog.do_histogram_many("sizes", &values, prima_datadog::EMPTY_TAGS);
+    let mut received = Vec::new();
+    while let Some(datagram) = read_string_from(&socket) {
+        assert!(datagram.len() < 1100, "{}", datagram);
+        let packed = datagram
+            .strip_prefix("packed.sizes:")
+            .unwrap()
+            .strip_suffix("|h")
+            .unwrap();
+        received.extend(packed.split(':').map(|value| value.parse::<u64>().unwrap()));
+    }
+    assert_eq!(received, values);
+}
+
+#[test]
+fn batched_values_of_the_same_context_are_coalesced() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "coalesced").with_batching_options(BATCHING_OPTIONS);
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    datadog.do_distribution("latency", 1, ["a:b"]);
+    datadog.do_distribution("latency", 2, ["a:b"]);
+    datadog.do_distribution("latency", 3, ["c:d"]);
+    datadog.do_histogram("latency", 4, ["c:d"]);
+    datadog.do_incr("requests", prima_datadog::EMPTY_TAGS);
+    datadog.do_histogram("latency", 5, ["c:d"]);
+    datadog.do_histogram("latency", 6, ["c:d"]);
+    datadog.do_flush().unwrap();
+
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some(
+            "coalesced.latency:1:2|d|#a:b\n\
+             coalesced.latency:3|d|#c:d\n\
+             coalesced.latency:4|h|#c:d\n\
+             coalesced.requests:1|c\n\
+             coalesced.latency:5:6|h|#c:d"
+        )
+    );
+}
+
+#[test]
+fn counts_gauges_and_sets_are_aggregated_by_context() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "aggregated")
+        .with_sample_rate("requests", 0.5)
+        .without_telemetry();
+    let client = AggregatingClient::new(
+        StatsdClient::new(&configuration).unwrap(),
+        AggregationOptions {
+            flush_interval: Duration::from_secs(3600),
+            sketches: None,
+        },
+    )
+    .unwrap();
+    let datadog = Datadog::new(client, TagTrackerConfiguration::new());
+
+    for _ in 0..100 {
+        datadog.do_incr("requests", ["status:200"]);
+    }
+    datadog.do_count("requests", 5, ["status:500"]);
+    datadog.do_decr("requests", ["status:500"]);
+    datadog.do_gauge("queue", 1, prima_datadog::EMPTY_TAGS);
+    datadog.do_gauge("queue", 2, prima_datadog::EMPTY_TAGS);
+    datadog.do_set("users", "a", prima_datadog::EMPTY_TAGS);
+    datadog.do_set("users", "a", prima_datadog::EMPTY_TAGS);
+    datadog.do_timing("latency", 10, prima_datadog::EMPTY_TAGS);
+    assert_eq!(read_string_from(&socket).as_deref(), Some("aggregated.latency:10|ms"));
+    assert_eq!(read_string_from(&socket), None);
+
+    datadog.do_flush().unwrap();
+    let mut lines: Vec<String> = std::iter::from_fn(|| read_string_from(&socket)).collect();
+    lines.sort();
+
+    assert_eq!(
+        lines,
+        vec![
+            "aggregated.queue:2|g",
+            "aggregated.requests:100|c|#status:200",
+            "aggregated.requests:4|c|#status:500",
+            "aggregated.users:a|s",
+        ]
+    );
+}
+
+#[test]
+fn histograms_and_distributions_are_summarized_by_sketches() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "sketched").without_telemetry();
+    let client = AggregatingClient::new(
+        StatsdClient::new(&configuration).unwrap(),
+        AggregationOptions {
+            flush_interval: Duration::from_secs(3600),
+            sketches: Some(SketchOptions {
+                relative_accuracy: 0.01,
+                percentiles: vec![0.5, 0.9],
+            }),
+        },
+    )
+    .unwrap();
+    let datadog = Datadog::new(client, TagTrackerConfiguration::new());
+
+    for value in 1..=9 {
+        datadog.do_distribution("latency", value, ["a:b"]);
+    }
+    datadog.do_histogram_many("size", &[10, 10], prima_datadog::EMPTY_TAGS);
+    datadog.do_histogram("size", "not a number", prima_datadog::EMPTY_TAGS);
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some("sketched.size:not a number|h")
+    );
+    assert_eq!(read_string_from(&socket), None);
+
+    datadog.do_flush().unwrap();
+    let gauges: HashMap<String, f64> = std::iter::from_fn(|| read_string_from(&socket))
+        .map(|line| {
+            let (metric, rest) = line.split_once(':').unwrap();
+            assert!(rest.contains("|g"), "{} is not a gauge", line);
+            (metric.to_string(), rest.split('|').next().unwrap().parse().unwrap())
+        })
+        .collect();
+
+    assert_eq!(gauges.len(), 12);
+    assert_eq!(gauges["sketched.latency.count"], 9.0);
+    assert_eq!(gauges["sketched.latency.min"], 1.0);
+    assert_eq!(gauges["sketched.latency.max"], 9.0);
+    assert_eq!(gauges["sketched.latency.avg"], 5.0);
+    assert!((gauges["sketched.latency.median"] - 5.0).abs() <= 0.05);
+    assert!((gauges["sketched.latency.90percentile"] - 8.0).abs() <= 0.08);
+    assert_eq!(gauges["sketched.size.count"], 2.0);
+    assert_eq!(gauges["sketched.size.median"], 10.0);
+}
+
+#[test]
+fn invalid_sketch_options_are_rejected() {
+    let configuration = Configuration::new("127.0.0.1:8125", "sketched").without_telemetry();
+    for (relative_accuracy, percentiles) in [
+        (0.0, vec![0.5]),
+        (1.0, vec![0.5]),
+        (0.01, vec![1.5]),
+        (0.01, vec![f64::NAN]),
+        (0.01, vec![0.5, 0.999]),
+    ] {
+        let result = AggregatingClient::new(
+            StatsdClient::new(&configuration).unwrap(),
+            AggregationOptions {
+                flush_interval: Duration::from_secs(3600),
+                sketches: Some(SketchOptions {
+                    relative_accuracy,
+                    percentiles,
+                }),
+            },
+        );
+        assert!(matches!(result, Err(Error::InvalidSketchOptions(_))));
+    }
+}
+
+#[test]
+fn timestamps_are_sent_for_counts_and_gauges() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "replay");
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+    let timestamp = UNIX_EPOCH + Duration::from_secs(1656581409);
+
+    datadog.do_gauge_at("queue", 12, timestamp, ["a:b"]);
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some("replay.queue:12|g|#a:b|T1656581409")
+    );
+    datadog.do_count_at("jobs", 3, timestamp, prima_datadog::EMPTY_TAGS);
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some("replay.jobs:3|c|T1656581409")
+    );
+}
+
+#[test]
+fn container_id_is_appended_to_every_payload() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "origin").with_container_id("3726184226f5d314");
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    datadog.do_incr("requests", ["a:b"]);
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some("origin.requests:1|c|#a:b|c:3726184226f5d314")
+    );
+    datadog.do_event("title", "text", prima_datadog::EMPTY_TAGS);
+    assert_eq!(
+        read_string_from(&socket).as_deref(),
+        Some("_e{5,4}:title|text|c:3726184226f5d314")
+    );
+}
+
+/// A socket path in the temporary directory, removed if it already exists
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("prima_datadog_{}_{}.socket", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn read_frame_from(listener: &UnixListener) -> (UnixStream, String) {
+    let (mut stream, _) = listener.accept().unwrap();
+    stream.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+    let frame = read_frame(&mut stream);
+    (stream, frame)
+}
+
+fn read_frame(stream: &mut UnixStream) -> String {
+    let mut length = [0; 4];
+    stream.read_exact(&mut length).unwrap();
+    let mut payload = vec![0; u32::from_le_bytes(length) as usize];
+    stream.read_exact(&mut payload).unwrap();
+    String::from_utf8(payload).unwrap()
+}
+
+#[test]
+fn metrics_are_sent_over_a_unix_datagram_socket() {
+    let path = socket_path("datagram");
+    let configuration = Configuration::new("", "uds").with_socket_path(format!("unix://{}", path.display()));
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    // The socket doesn't exist yet, so the metric is dropped
+    datadog.do_incr("before_agent", prima_datadog::EMPTY_TAGS);
+    let socket = UnixDatagram::bind(&path).unwrap();
+    socket.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+    datadog.do_incr("requests", ["a:b"]);
+
+    let mut buf = [0; 1024];
+    let length = socket.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..length], b"uds.requests:1|c|#a:b");
+    assert_eq!(datadog.do_stats().packets_dropped, 1);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn metrics_are_sent_over_a_unix_stream_socket_with_their_length() {
+    let path = socket_path("stream");
+    let listener = UnixListener::bind(&path).unwrap();
+    let configuration = Configuration::new("", "stream").with_socket_path(format!("unixstream://{}", path.display()));
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    datadog.do_incr("first", ["a:b"]);
+    datadog.do_gauge("second", 12, prima_datadog::EMPTY_TAGS);
+    let (mut stream, frame) = read_frame_from(&listener);
+    assert_eq!(frame, "stream.first:1|c|#a:b");
+    assert_eq!(read_frame(&mut stream), "stream.second:12|g");
+
+    // The client reconnects when the agent restarts
+    drop(stream);
+    drop(listener);
+    std::fs::remove_file(&path).unwrap();
+    let listener = UnixListener::bind(&path).unwrap();
+    datadog.do_incr("after_restart", prima_datadog::EMPTY_TAGS);
+    let (_, frame) = read_frame_from(&listener);
+    assert_eq!(frame, "stream.after_restart:1|c");
+    assert_eq!(datadog.do_stats().packets_dropped, 0);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn udp_is_used_when_the_socket_is_absent() {
+    let (socket, address) = bind_receiver();
+    let configuration = Configuration::new(&address, "fallback")
+        .with_socket_path(format!("unix://{}", socket_path("absent").display()))
+        .with_udp_fallback();
+    let datadog = Datadog::new(
+        StatsdClient::new(&configuration).unwrap(),
+        TagTrackerConfiguration::new(),
+    );
+
+    datadog.do_incr("requests", prima_datadog::EMPTY_TAGS);
+
+    assert_eq!(read_string_from(&socket).as_deref(), Some("fallback.requests:1|c"));
+}
@@ -0,0 +1,171 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use prima_datadog::{
+    configuration::Configuration,
+    context::{self, ContextFutureExt},
+    count, distribution,
+    error::Error,
+    gauge, incr, service_check,
+    testing::{self, MetricKind, Record},
+    time, timing, Datadog, ServiceStatus, EMPTY_TAGS,
+};
+use serial_test::serial;
+
+#[test]
+#[serial]
+fn macros_are_recorded_by_the_global_recorder() {
+    let recorder = testing::install().unwrap();
+    recorder.clear();
+
+    incr!("test"; "some" => "data");
+    timing!("test_timing", 20);
+    let output = time!("test_time", || 42);
+    service_check!("test_check", ServiceStatus::Critical);
+
+    assert_eq!(output, 42);
+    assert_eq!(
+        recorder.records_named("test"),
+        vec![Record {
+            kind: MetricKind::Incr,
+            name: "test".to_string(),
+            value: "1".to_string(),
+            tags: vec!["some:data".to_string()],
+            sample_rate: None,
+            timestamp: None,
+        }]
+    );
+    assert_eq!(recorder.records_named("test_timing")[0].value, "20");
+    assert_eq!(recorder.records_named("test_time")[0].kind, MetricKind::Timing);
+    assert_eq!(recorder.records_named("test_check")[0].value, "2");
+}
+
+#[test]
+#[serial]
+fn install_returns_the_same_recorder() {
+    let recorder = testing::install().unwrap();
+    recorder.clear();
+
+    Datadog::incr("test", ["first:call"]);
+    let records = testing::install().unwrap().take();
+
+    assert_eq!(records.len(), 1);
+    assert!(records[0].has_tag("first:call"));
+    assert!(recorder.records().is_empty());
+}
+
+#[test]
+#[serial]
+fn init_fails_once_the_recorder_is_installed() {
+    testing::install().unwrap();
+
+    let result = Datadog::init(Configuration::new("127.0.0.1:8125", "namespace"));
+
+    assert!(matches!(result, Err(Error::OnceCellAlreadyInitialized)));
+}
+
+#[test]
+#[serial]
+fn sample_rates_are_recorded() {
+    let recorder = testing::install().unwrap();
+    recorder.clear();
+
+    incr!("sampled_incr", @0.1; "some" => "data");
+    count!("sampled_count", 5, @0.5);
+    distribution!("sampled_distribution", "1.5", @0.25; "key" => 42);
+    timing!("not_sampled", 20);
+
+    let incr = &recorder.records_named("sampled_incr")[0];
+    assert_eq!(incr.sample_rate, Some(0.1));
+    assert!(incr.has_tag("some:data"));
+    assert_eq!(recorder.records_named("sampled_count")[0].value, "5");
+    assert_eq!(recorder.records_named("sampled_count")[0].sample_rate, Some(0.5));
+    let distribution = &recorder.records_named("sampled_distribution")[0];
+    assert_eq!(distribution.sample_rate, Some(0.25));
+    assert!(distribution.has_tag("key:42"));
+    assert_eq!(recorder.records_named("not_sampled")[0].sample_rate, None);
+}
+
+#[test]
+#[serial]
+fn timestamps_are_recorded() {
+    let recorder = testing::install().unwrap();
+    recorder.clear();
+
+    let timestamp = UNIX_EPOCH + Duration::from_secs(1656581409);
+    gauge!("replayed_gauge", 1.5, at timestamp; "some" => "data");
+    count!("replayed_count", 3, at timestamp);
+    count!("replayed_future", 3, at SystemTime::now() + Duration::from_secs(3600));
+
+    let gauge = &recorder.records_named("replayed_gauge")[0];
+    assert_eq!(gauge.kind, MetricKind::Gauge);
+    assert_eq!(gauge.timestamp, Some(1656581409));
+    assert!(gauge.has_tag("some:data"));
+    assert_eq!(recorder.records_named("replayed_count")[0].timestamp, Some(1656581409));
+    assert!(recorder.records_named("replayed_future").is_empty());
+}
+
+#[test]
+#[serial]
+fn scoped_handles_prefix_metrics_and_add_tags() {
+    let recorder = testing::install().unwrap();
+    recorder.clear();
+
+    let billing = Datadog::scoped("billing").with_tag("component", "pricing");
+    billing.incr("invoices", ["country:it"]);
+    billing.scoped("refunds").timing("requested", 10, EMPTY_TAGS);
+    billing.service_check("billing_check", ServiceStatus::OK, EMPTY_TAGS, None);
+
+    let invoices = recorder.records_named("billing.invoices");
+    assert_eq!(invoices.len(), 1);
+    assert_eq!(invoices[0].tags, vec!["country:it", "component:pricing"]);
+    let refunds = recorder.records_named("billing.refunds.requested");
+    assert_eq!(refunds[0].kind, MetricKind::Timing);
+    assert!(refunds[0].has_tag("component:pricing"));
+    // Service checks are not prefixed
+    assert!(recorder.records_named("billing_check")[0].has_tag("component:pricing"));
+}
+
+#[test]
+#[serial]
+fn context_tags_are_appended_inside_the_scope() {
+    let recorder = testing::install().unwrap();
+    recorder.clear();
+
+    context::with_tags(&["route:/quote"], || {
+        incr!("context_incr"; "some" => "data");
+        context::with_tags(&["tenant:prima"], || {
+            let _guard = Datadog::enter_timing("context_guard", EMPTY_TAGS);
+        });
+        time!("context_time", || ());
+    });
+    incr!("context_outside");
+
+    assert_eq!(
+        recorder.records_named("context_incr")[0].tags,
+        vec!["some:data", "route:/quote"]
+    );
+    assert_eq!(
+        recorder.records_named("context_guard")[0].tags,
+        vec!["route:/quote", "tenant:prima"]
+    );
+    assert!(recorder.records_named("context_time")[0].has_tag("route:/quote"));
+    assert!(recorder.records_named("context_outside")[0].tags.is_empty());
+}
+
+#[tokio::test(flavor = "current_thread")]
+#[serial]
+async fn context_tags_follow_spawned_futures() {
+    let recorder = testing::install().unwrap();
+    recorder.clear();
+
+    let task = async {
+        tokio::task::yield_now().await;
+        incr!("context_task");
+    }
+    .with_tags(["job:billing"]);
+    tokio::spawn(task).await.unwrap();
+    incr!("context_after_task");
+
+    assert!(recorder.records_named("context_task")[0].has_tag("job:billing"));
+    assert!(recorder.records_named("context_after_task")[0].tags.is_empty());
+}
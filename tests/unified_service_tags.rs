@@ -0,0 +1,22 @@
+//! The environment variables are global to the process, so these tests have their own binary
+
+use prima_datadog::{configuration::Configuration, error::Error, Datadog};
+
+#[test]
+fn init_fails_in_production_without_service_and_version_when_validated() {
+    std::env::remove_var("DD_SERVICE");
+    std::env::remove_var("DD_VERSION");
+    std::env::set_var("DD_ENV", "PRODUCTION");
+
+    // Only `from_env` reads the unified service tags from the environment
+    assert!(Configuration::new("127.0.0.1:8125", "namespace").validate().is_ok());
+
+    let result = Datadog::init(Configuration::from_env().unwrap().with_validation());
+    assert!(matches!(
+        result,
+        Err(Error::MissingUnifiedServiceTags(missing)) if missing == ["service", "version"]
+    ));
+
+    // The validation is opt-in
+    assert!(Datadog::init(Configuration::from_env().unwrap()).is_ok());
+}